@@ -0,0 +1,46 @@
+use crate::util::{generic_client_params, type_is_client};
+
+// Determine the client from the function parameters.
+//
+// A parameter named `client` is preferred, then a parameter with a client type, and finally `self.client` for methods.
+pub fn infer_client(signature: &syn::Signature) -> Result<syn::Expr, syn::Error> {
+	let params: Vec<(&syn::Ident, &syn::Type)> = signature.inputs.iter()
+		.filter_map(|input| match input {
+			syn::FnArg::Typed(input) => match &*input.pat {
+				syn::Pat::Ident(pat) => Some((&pat.ident, &*input.ty)),
+				_ => None,
+			},
+			syn::FnArg::Receiver(_) => None,
+		})
+		.collect();
+
+	if let Some((ident, _)) = params.iter().find(|(ident, _)| *ident == "client") {
+		return Ok(syn::parse_quote!(#ident));
+	}
+
+	let client_generics = generic_client_params(&signature.generics);
+	let candidates: Vec<&syn::Ident> = params.iter()
+		.filter(|(_, typ)| type_is_client(typ, &client_generics))
+		.map(|(ident, _)| *ident)
+		.collect();
+	match candidates.as_slice() {
+		[] => (),
+		[ident] => return Ok(syn::parse_quote!(#ident)),
+		[_, second, ..] => {
+			let names: Vec<String> = candidates.iter().map(|ident| format!("`{ident}`")).collect();
+			return Err(syn::Error::new(second.span(), format!(
+				"#[inline_sql]: multiple parameters could be the client: {}\n\nNote: Select one with `#[inline_sql(client = ...)]`.",
+				names.join(", "),
+			)));
+		},
+	}
+
+	if signature.receiver().is_some() {
+		return Ok(syn::parse_quote!(self.client));
+	}
+	Err(syn::Error::new(signature.ident.span(), concat!(
+		"#[inline_sql]: could not find the client in the function parameters\n\n",
+		"Note: Add a parameter with a client type like `&tokio_postgres::Client`, ",
+		"or select the client with `#[inline_sql(client = ...)]`.",
+	)))
+}
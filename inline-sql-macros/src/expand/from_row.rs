@@ -0,0 +1,202 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::input::{Column, Decode, FromRowField, FromRowStruct, RowBackend};
+
+pub fn expand_from_row(input: FromRowStruct) -> TokenStream {
+	input.backends.iter()
+		.map(|backend| match backend {
+			RowBackend::Postgres => expand_postgres(&input),
+			RowBackend::Rusqlite => expand_rusqlite(&input),
+		})
+		.collect()
+}
+
+fn expand_postgres(input: &FromRowStruct) -> TokenStream {
+	let pg = quote!(::inline_sql::macro_export__::pg);
+	let row_type = quote!(#pg::Row);
+	let has_column = |column: &Column| match column {
+		Column::Name(_) => {
+			let column = column_name(column);
+			quote!(row.columns().iter().any(|column| column.name() == #column))
+		},
+		Column::Index(index) => quote!(#index < row.len()),
+	};
+
+	let fields = input.fields.iter().map(|field| {
+		let FromRowField { member, typ, column, default, decode } = field;
+		let column_name = column_name(column);
+		let value = match decode {
+			Decode::Column => quote_spanned!(typ.span() => row.try_get::<_, #typ>(#column_name)?),
+			Decode::TryFrom(source) => quote_spanned!(source.span() => {
+				row.try_get::<_, ::inline_sql::macro_export__::TryFromSql<#source, #typ>>(#column_name)?.value
+			}),
+			Decode::Flatten(field_prefix) => flatten_row(&row_type, typ, field_prefix),
+			Decode::Skip => quote!(::core::default::Default::default()),
+		};
+		let value = or_default(*default, has_column(column), value);
+		quote!(#member: #value)
+	});
+
+	let null_checks = input.fields.iter().filter_map(|field| {
+		let FromRowField { typ, column, default, decode, .. } = field;
+		let column_name = column_name(column);
+		let is_null = match decode {
+			Decode::Column | Decode::TryFrom(_) => quote!(row.try_get::<_, ::inline_sql::macro_export__::IsNull>(#column_name)?.0),
+			Decode::Flatten(field_prefix) => flatten_is_null(&row_type, typ, field_prefix),
+			Decode::Skip => return None,
+		};
+		Some(missing_or_null(*default, has_column(column), is_null))
+	});
+
+	// `TryFrom` takes the row by value, like the row stream of the backend.
+	let try_from = (quote!(#pg::Row), quote!(&row));
+	expand_impls(input, &row_type, &quote!(#pg::Error), try_from, fields, null_checks)
+}
+
+fn expand_rusqlite(input: &FromRowStruct) -> TokenStream {
+	let row_type = quote!(::rusqlite::Row<'_>);
+	let statement = quote!(<::rusqlite::Row<'_> as ::core::convert::AsRef<::rusqlite::Statement<'_>>>::as_ref(row));
+	let has_column = |column: &Column| match column {
+		Column::Name(_) => {
+			let column = column_name(column);
+			quote!(#statement.column_index(#column).is_ok())
+		},
+		Column::Index(index) => quote!(#index < #statement.column_count()),
+	};
+
+	let fields = input.fields.iter().map(|field| {
+		let FromRowField { member, typ, column, default, decode } = field;
+		let column_name = column_name(column);
+		let index = match column {
+			Column::Name(_) => quote!(#statement.column_index(#column_name)?),
+			Column::Index(index) => quote!(#index),
+		};
+		let value = match decode {
+			Decode::Column => quote_spanned!(typ.span() => row.get::<_, #typ>(#column_name)?),
+			// Conversion errors are reported like errors from `FromSql`.
+			Decode::TryFrom(source) => quote_spanned!(source.span() => {
+				let value = row.get::<_, #source>(#column_name)?;
+				match ::core::convert::TryFrom::try_from(value) {
+					::core::result::Result::Ok(value) => value,
+					::core::result::Result::Err(e) => {
+						let data_type = row.get_ref(#column_name)?.data_type();
+						return ::core::result::Result::Err(::rusqlite::Error::FromSqlConversionFailure(#index, data_type, ::std::boxed::Box::new(e)));
+					},
+				}
+			}),
+			Decode::Flatten(field_prefix) => flatten_row(&row_type, typ, field_prefix),
+			Decode::Skip => quote!(::core::default::Default::default()),
+		};
+		let value = or_default(*default, has_column(column), value);
+		quote!(#member: #value)
+	});
+
+	let null_checks = input.fields.iter().filter_map(|field| {
+		let FromRowField { typ, column, default, decode, .. } = field;
+		let column_name = column_name(column);
+		let is_null = match decode {
+			Decode::Column | Decode::TryFrom(_) => quote!(::core::matches!(row.get_ref(#column_name)?, ::rusqlite::types::ValueRef::Null)),
+			Decode::Flatten(field_prefix) => flatten_is_null(&row_type, typ, field_prefix),
+			Decode::Skip => return None,
+		};
+		Some(missing_or_null(*default, has_column(column), is_null))
+	});
+
+	// `TryFrom` takes the row by reference, since rusqlite rows borrow the statement.
+	let try_from = (quote!(&::rusqlite::Row<'_>), quote!(row));
+	expand_impls(input, &row_type, &quote!(::rusqlite::Error), try_from, fields, null_checks)
+}
+
+fn expand_impls(
+	input: &FromRowStruct,
+	row_type: &TokenStream,
+	error_type: &TokenStream,
+	try_from: (TokenStream, TokenStream),
+	fields: impl Iterator<Item = TokenStream>,
+	null_checks: impl Iterator<Item = TokenStream>,
+) -> TokenStream {
+	let ident = &input.ident;
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	let (try_from_type, try_from_arg) = try_from;
+
+	quote! {
+		impl #impl_generics ::inline_sql::FromRow<#row_type> for #ident #type_generics #where_clause {
+			type Error = #error_type;
+
+			fn from_row(row: &#row_type) -> ::core::result::Result<Self, Self::Error> {
+				<Self as ::inline_sql::FromRow<#row_type>>::from_row_prefixed(row, "")
+			}
+
+			fn from_row_prefixed(row: &#row_type, prefix: &str) -> ::core::result::Result<Self, Self::Error> {
+				let _ = prefix;
+				::core::result::Result::Ok(Self {
+					#(#fields,)*
+				})
+			}
+
+			fn is_null(row: &#row_type, prefix: &str) -> ::core::result::Result<bool, Self::Error> {
+				let _ = (row, prefix);
+				::core::result::Result::Ok(true #(&& #null_checks)*)
+			}
+		}
+
+		impl #impl_generics ::core::convert::TryFrom<#try_from_type> for #ident #type_generics #where_clause {
+			type Error = #error_type;
+
+			fn try_from(row: #try_from_type) -> ::core::result::Result<Self, Self::Error> {
+				<Self as ::inline_sql::FromRow<#row_type>>::from_row(#try_from_arg)
+			}
+		}
+	}
+}
+
+// Get the name or index of a column, with the prefix of the struct for named columns.
+fn column_name(column: &Column) -> TokenStream {
+	match column {
+		Column::Name(name) => quote!(&*::inline_sql::macro_export__::prefixed(prefix, #name)),
+		Column::Index(index) => quote!(#index),
+	}
+}
+
+fn field_prefix(field_prefix: &Option<syn::LitStr>) -> TokenStream {
+	match field_prefix {
+		Some(field_prefix) => quote!(&*::inline_sql::macro_export__::prefixed(prefix, #field_prefix)),
+		None => quote!(prefix),
+	}
+}
+
+fn flatten_row(row_type: &TokenStream, typ: &syn::Type, prefix: &Option<syn::LitStr>) -> TokenStream {
+	let prefix = field_prefix(prefix);
+	quote_spanned!(typ.span() => <#typ as ::inline_sql::FromRow<#row_type>>::from_row_prefixed(row, #prefix)?)
+}
+
+fn flatten_is_null(row_type: &TokenStream, typ: &syn::Type, prefix: &Option<syn::LitStr>) -> TokenStream {
+	let prefix = field_prefix(prefix);
+	quote_spanned!(typ.span() => <#typ as ::inline_sql::FromRow<#row_type>>::is_null(row, #prefix)?)
+}
+
+// Fields with the `default` attribute use `Default::default()` if the column is missing from the row.
+fn or_default(default: bool, has_column: TokenStream, value: TokenStream) -> TokenStream {
+	if default {
+		quote! {
+			if #has_column {
+				#value
+			} else {
+				::core::default::Default::default()
+			}
+		}
+	} else {
+		value
+	}
+}
+
+// Missing columns of fields with the `default` attribute count as NULL.
+fn missing_or_null(default: bool, has_column: TokenStream, is_null: TokenStream) -> TokenStream {
+	if default {
+		quote!((!(#has_column) || #is_null))
+	} else {
+		quote!(#is_null)
+	}
+}
@@ -0,0 +1,388 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+
+use crate::input::{Arguments, Backend, SqlFile, SqlFunction, QueryType, Query, QueryMacro, RowType};
+use crate::util::{return_type_err_span, type_result_err};
+
+mod client;
+mod from_row;
+mod params;
+mod postgres;
+mod rusqlite;
+mod sqlx;
+
+pub use from_row::expand_from_row;
+
+pub struct Context<'a> {
+	pub signature: &'a syn::Signature,
+	pub client: syn::Expr,
+	pub query: String,
+	pub placeholders: Vec<syn::Expr>,
+	pub map_row: Option<syn::Expr>,
+	pub handle_err: TokenStream,
+	// The error type that rows are converted into, if it is written in the signature.
+	pub error: Option<&'a syn::Type>,
+}
+
+impl Context<'_> {
+	pub fn row_count_error(&self, variant: &str) -> TokenStream {
+		let span = return_type_err_span(self.signature);
+		let variant = syn::Ident::new(variant, span);
+		self.return_error(quote_spanned!(span => ::inline_sql::RowCountError), quote_spanned!(span => ::inline_sql::RowCountError::#variant))
+	}
+
+	pub fn column_count_error(&self, expected: usize, actual: TokenStream) -> TokenStream {
+		let span = return_type_err_span(self.signature);
+		self.return_error(quote_spanned!(span => ::inline_sql::ColumnCountError), quote_spanned!(span => ::inline_sql::ColumnCountError {
+			expected: #expected,
+			actual: #actual,
+		}))
+	}
+
+	// Return an error of this crate, converted into the error type of the function or mapped stream.
+	//
+	// The conversion goes through a function with a `From` bound that is given the error type from the signature,
+	// so a missing `From` implementation is reported on that type instead of on the attribute.
+	fn return_error(&self, error_type: TokenStream, error: TokenStream) -> TokenStream {
+		let span = return_type_err_span(self.signature);
+		let target = match self.error {
+			Some(target) => target.to_token_stream(),
+			None => quote_spanned!(span => _),
+		};
+		quote_spanned!(span => {
+			fn convert_error<E: ::core::convert::From<#error_type>>(error: #error_type) -> E {
+				::core::convert::From::from(error)
+			}
+			return ::core::result::Result::Err(convert_error::<#target>(#error))
+		})
+	}
+
+	// Rows of a mapped stream are converted lazily, so errors are returned from the closure instead of the function.
+	pub fn map_stream(&self, stream: TokenStream, row: RowType, error: &syn::Type, boxed: bool, map_elem: TokenStream) -> TokenStream {
+		let handle_err = &self.handle_err;
+		let stream = quote! {
+			#stream.map(move |result| -> ::core::result::Result<#row, #error> {
+				let row = #handle_err;
+				let elem = #map_elem;
+				::core::result::Result::Ok(elem)
+			})
+		};
+		if boxed {
+			quote!(::std::boxed::Box::pin(#stream))
+		} else {
+			stream
+		}
+	}
+}
+
+// Get the output of a query that gathered its rows in a `Vec` named `output`.
+//
+// Rows for `Collect` queries are gathered in a `Vec` first, since converting a row may return early from the function.
+// The `Vec` is then converted into the collection of the return type.
+pub fn collect_output(query_type: &QueryType) -> TokenStream {
+	match query_type {
+		QueryType::Collect(_) => quote!(::core::iter::FromIterator::from_iter(output)),
+		_ => quote!(output),
+	}
+}
+
+pub fn expand_sql_impl(errors: &mut Vec<syn::Error>, mut item: syn::ItemImpl, args: Arguments) -> TokenStream {
+	if let Some(impl_for) = &args.impl_for {
+		errors.push(syn::Error::new_spanned(impl_for, "#[inline_sql]: the `impl_for` argument can only be used on a trait"));
+	}
+	// Methods with their own `#[inline_sql]` attribute or a `query!` body are SQL functions, other items are left alone.
+	for impl_item in &mut item.items {
+		let method = match impl_item {
+			syn::ImplItem::Fn(method) => method,
+			_ => continue,
+		};
+		let method_args = Arguments::take_from_attributes(errors, &mut method.attrs);
+		if method_args.is_none() && !SqlFunction::is_query_block(&method.block) {
+			continue;
+		}
+		let mut method_args = method_args.unwrap_or_default();
+		method_args.inherit(&args);
+		let method = method.clone();
+		let function = SqlFunction::from_block(method.attrs, method.vis, method.defaultness, method.sig, method.block);
+		*impl_item = syn::ImplItem::Verbatim(expand_sql_function(errors, function, method_args));
+	}
+	item.into_token_stream()
+}
+
+pub fn expand_sql_trait(errors: &mut Vec<syn::Error>, mut item: syn::ItemTrait, args: Arguments) -> TokenStream {
+	// Methods with their own `#[inline_sql]` attribute or a `query!` body are declared in the trait,
+	// and implemented for the client type, which is available as `self`.
+	let mut methods = Vec::new();
+	let mut missing_default = false;
+	for trait_item in &mut item.items {
+		let method = match trait_item {
+			syn::TraitItem::Fn(method) => method,
+			_ => continue,
+		};
+		let method_args = Arguments::take_from_attributes(errors, &mut method.attrs);
+		let is_query_block = method.default.as_ref().is_some_and(SqlFunction::is_query_block);
+		if method_args.is_none() && !is_query_block {
+			// The generated implementation can only provide SQL functions.
+			if method.default.is_none() {
+				errors.push(syn::Error::new_spanned(&method.sig.ident, "#[inline_sql]: methods of an #[inline_sql] trait need a `query!` body or a default implementation"));
+				missing_default = true;
+			}
+			continue;
+		}
+		let mut method_args = method_args.unwrap_or_default();
+		method_args.inherit(&args);
+		// Methods without a receiver find their client like free functions.
+		if method_args.client.is_none() && method.sig.receiver().is_some() {
+			method_args.client = Some(syn::parse_quote!(self));
+		}
+		let block = method.default.take().unwrap_or_else(|| syn::parse_quote!({}));
+		method.semi_token = Some(Default::default());
+		methods.push((method.clone(), block, method_args));
+	}
+	if missing_default {
+		return item.into_token_stream();
+	}
+
+	// Without `impl_for`, the trait is implemented for every client of the backend.
+	let mut generics = item.generics.clone();
+	let client_type = match &args.impl_for {
+		Some(impl_for) => impl_for.to_token_stream(),
+		None => {
+			// The bound follows the backend of the methods, so they must all agree on it.
+			let method_backend = |(method, _, args): &(syn::TraitItemFn, syn::Block, Arguments)| {
+				args.backend.unwrap_or_else(|| Backend::default_for(&method.sig))
+			};
+			let backend = args.backend
+				.or_else(|| methods.first().map(method_backend))
+				.unwrap_or(Backend::TokioPostgres);
+			let mut mismatched = false;
+			for method in &methods {
+				let method_backend = method_backend(method);
+				if method_backend != backend {
+					errors.push(syn::Error::new_spanned(&method.0.sig.ident, format!(
+						"#[inline_sql]: this method uses the `{}` backend, but the trait is implemented for the clients of the `{}` backend, give the `backend` or `impl_for` argument on the trait",
+						method_backend.name(),
+						backend.name(),
+					)));
+					mismatched = true;
+				}
+			}
+			if mismatched {
+				return item.into_token_stream();
+			}
+			let bound = match backend {
+				Backend::TokioPostgres => quote!(::tokio_postgres::GenericClient),
+				Backend::Postgres => quote!(::postgres::GenericClient),
+				Backend::Rusqlite | Backend::Sqlx => {
+					errors.push(syn::Error::new_spanned(&item.ident, format!("#[inline_sql]: the `{}` backend has no generic client trait, use the `impl_for` argument to select the client type", backend.name())));
+					return item.into_token_stream();
+				},
+			};
+			generics.params.push(syn::parse_quote!(__InlineSqlClient: #bound));
+			quote!(__InlineSqlClient)
+		},
+	};
+
+	let methods: Vec<TokenStream> = methods.into_iter()
+		.map(|(method, block, method_args)| {
+			let function = SqlFunction::from_block(method.attrs, syn::Visibility::Inherited, None, method.sig, block);
+			expand_sql_function(errors, function, method_args)
+		})
+		.collect();
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	let (_, type_generics, _) = item.generics.split_for_impl();
+	let unsafety = &item.unsafety;
+	let trait_name = &item.ident;
+	quote! {
+		#item
+		#unsafety impl #impl_generics #trait_name #type_generics for #client_type #where_clause {
+			#(#methods)*
+		}
+	}
+}
+
+pub fn expand_sql_function(errors: &mut Vec<syn::Error>, function: SqlFunction, args: Arguments) -> TokenStream {
+	let SqlFunction {
+		attributes,
+		visibility,
+		defaultness,
+		signature,
+		brace_token,
+		body,
+	} = function;
+
+	let Arguments {
+		client,
+		map_row,
+		map_err,
+		group_by,
+		backend,
+		kind,
+		row,
+		scalar,
+		file,
+		name,
+		placeholder,
+		allow_unused,
+		impl_for,
+	} = args;
+
+	if let Some(impl_for) = &impl_for {
+		errors.push(syn::Error::new_spanned(impl_for, "#[inline_sql]: the `impl_for` argument can only be used on a trait"));
+	}
+
+	let backend = backend.unwrap_or_else(|| Backend::default_for(&signature));
+	if backend.is_async() && signature.asyncness.is_none() {
+		errors.push(syn::Error::new_spanned(signature.fn_token, format!("#[inline_sql]: the `{}` backend can only be used with `async` functions", backend.name())));
+	} else if !backend.is_async() && signature.asyncness.is_some() {
+		errors.push(syn::Error::new_spanned(signature.asyncness, format!("#[inline_sql]: the `{}` backend can not be used with `async` functions", backend.name())));
+	}
+
+	// `map_row = serde` deserializes rows with the `serde` feature of `inline-sql`.
+	let map_row = match map_row {
+		Some(syn::Expr::Path(path)) if path.qself.is_none() && path.path.is_ident("serde") => {
+			if !matches!(backend, Backend::TokioPostgres | Backend::Postgres) {
+				errors.push(syn::Error::new_spanned(&path, format!("#[inline_sql]: `map_row = serde` is not supported for the `{}` backend", backend.name())));
+			}
+			Some(syn::parse_quote_spanned!(path.span() => ::inline_sql::macro_export__::serde_row))
+		},
+		map_row => map_row,
+	};
+
+	let sigil = match &placeholder {
+		None => '$',
+		Some(placeholder) => match placeholder.value() {
+			sigil @ ('$' | ':' | '@') => sigil,
+			_ => {
+				errors.push(syn::Error::new_spanned(placeholder, "#[inline_sql]: the placeholder sigil must be one of '$', ':' or '@'"));
+				'$'
+			},
+		},
+	};
+
+	// Queries from a file are tracked with `include_str!()`, so the crate is rebuilt when the file changes.
+	let mut track_file = None;
+	let query = match &file {
+		Some(file) => {
+			if !body.is_empty() {
+				errors.push(syn::Error::new_spanned(&body, "#[inline_sql]: the function body must be empty when the `file` argument is used"));
+			}
+			SqlFile::read(file).and_then(|sql_file| {
+				let path = &sql_file.path;
+				track_file = Some(quote!(const _: &str = ::core::include_str!(#path);));
+				let sql = match &name {
+					Some(name) => sql_file.select(name)?,
+					None => &sql_file.contents,
+				};
+				Query::from_sql(sql, file.span(), backend.placeholder_style(), sigil)
+			})
+		},
+		None => {
+			if let Some(name) = &name {
+				errors.push(syn::Error::new_spanned(name, "#[inline_sql]: the `name` argument can only be used together with the `file` argument"));
+			}
+			syn::parse2::<QueryMacro>(body)
+				.and_then(|x| Query::from_tokens(x.tokens.clone(), x.span(), backend.placeholder_style(), sigil))
+		},
+	};
+	let query = match query {
+		Ok(x) => x,
+		Err(e) => {
+			errors.push(e);
+			Query {
+				query: String::new(),
+				placeholders: Vec::new(),
+			}
+		},
+	};
+	let Query {
+		query,
+		placeholders
+	} = query;
+
+	let _ = brace_token;
+
+	let query_type = match &signature.output {
+		syn::ReturnType::Default => {
+			errors.push(syn::Error::new_spanned(&signature.ident, "#[inline_sql]: Function must return a `Result<_, _>`"));
+			None
+		},
+		syn::ReturnType::Type(_, typ) => {
+			let query_type = match kind {
+				Some(kind) => QueryType::from_kind(kind, row.as_ref(), typ, scalar),
+				None => QueryType::from_return_type(typ, scalar),
+			};
+			query_type
+				.map_err(|e| errors.push(e))
+				.ok()
+		}
+	};
+	let query_type = match (query_type, &group_by) {
+		(Some(query_type), Some(group_by)) => query_type.group_by(group_by, row.is_some())
+			.map_err(|e| errors.push(e))
+			.ok(),
+		(query_type, _) => query_type,
+	};
+	if let (None, Some(row)) = (kind, &row) {
+		errors.push(syn::Error::new_spanned(row, "#[inline_sql]: the `row` argument can only be used together with the `kind` argument"));
+	}
+
+	let client = client.unwrap_or_else(|| {
+		client::infer_client(&signature).unwrap_or_else(|e| {
+			errors.push(e);
+			syn::parse_quote!(client)
+		})
+	});
+
+	let mut other_uses = client.to_token_stream();
+	other_uses.extend(map_row.to_token_stream());
+	other_uses.extend(map_err.to_token_stream());
+	other_uses.extend(group_by.to_token_stream());
+	let unused_warnings = params::check_params(errors, &signature, &placeholders, sigil, other_uses, allow_unused);
+
+	let handle_err = match map_err {
+		Some(map_err) => quote_spanned!(map_err.span() => {
+			result.map_err(#map_err)?
+		}),
+		None => quote_spanned!(return_type_err_span(&signature) => {
+			#[allow(clippy::useless_conversion, clippy::question_mark)]
+			match result.map_err(::core::convert::From::from) {
+				Ok(x) => x,
+				Err(e) => return Err(e),
+			}
+		}),
+	};
+	let query_type = query_type.unwrap_or(QueryType::Execute);
+	let error = match (&query_type, &signature.output) {
+		(QueryType::MappedStream { error, .. }, _) => Some(*error),
+		(_, syn::ReturnType::Type(_, typ)) => type_result_err(typ),
+		(_, syn::ReturnType::Default) => None,
+	};
+	let context = Context {
+		signature: &signature,
+		client,
+		query,
+		placeholders,
+		map_row,
+		handle_err,
+		error,
+	};
+	let body = match backend {
+		Backend::TokioPostgres => postgres::expand_body(errors, context, query_type, true),
+		Backend::Postgres => postgres::expand_body(errors, context, query_type, false),
+		Backend::Rusqlite => rusqlite::expand_body(errors, context, query_type),
+		Backend::Sqlx => sqlx::expand_body(errors, context, query_type),
+	};
+
+	quote! {
+		#(#attributes)*
+		#visibility #defaultness #signature {
+			#[allow(unused_imports)]
+			use ::inline_sql::macro_export__::prelude::*;
+			#track_file
+			#unused_warnings
+			#body
+		}
+	}
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{quote_spanned, ToTokens};
+
+use crate::util::edit_distance;
+
+// Check that placeholders refer to function parameters, and warn about unused parameters.
+//
+// Only placeholders that are a plain name or field access are checked.
+// Names starting with an uppercase letter are assumed to be constants or statics.
+//
+// Unused parameters are reported with a deprecation warning, since proc macros can not emit warnings directly.
+// With `allow_unused`, or if an error was already reported, they are silently marked as used instead.
+// Parameters are also used if they appear in `other_uses`, or if their name starts with an underscore.
+pub fn check_params(
+	errors: &mut Vec<syn::Error>,
+	signature: &syn::Signature,
+	placeholders: &[syn::Expr],
+	sigil: char,
+	other_uses: TokenStream,
+	allow_unused: bool,
+) -> TokenStream {
+	let mut params = Vec::new();
+	for input in &signature.inputs {
+		if let syn::FnArg::Typed(input) = input {
+			pat_idents(&input.pat, &mut params);
+		}
+	}
+
+	for placeholder in placeholders {
+		let root = match placeholder_root(placeholder) {
+			Some(x) => x,
+			None => continue,
+		};
+		if root == "self" && signature.receiver().is_some() {
+			continue;
+		}
+		let name = root.to_string();
+		if name.starts_with(|c: char| c.is_uppercase()) || params.contains(&root) {
+			continue;
+		}
+		let suggestion = params.iter()
+			.map(|param| param.to_string())
+			.map(|param| (edit_distance(&name, &param), param))
+			.filter(|(distance, _)| *distance <= (name.len() / 3).max(1))
+			.min();
+		let message = match suggestion {
+			Some((_, param)) => format!("#[inline_sql]: unknown placeholder `{sigil}{name}`, did you mean `{sigil}{param}`?"),
+			None => format!("#[inline_sql]: unknown placeholder `{sigil}{name}`, it does not match any function parameter"),
+		};
+		errors.push(syn::Error::new(root.span(), message));
+	}
+
+	let mut used = BTreeSet::new();
+	for placeholder in placeholders {
+		collect_idents(&mut used, placeholder.to_token_stream());
+	}
+	collect_idents(&mut used, other_uses);
+
+	// Unused parameters are likely a consequence of earlier errors, like a client that could not be found.
+	let report_unused = !allow_unused && errors.is_empty();
+	let mut warnings = TokenStream::new();
+	for param in params {
+		let name = param.to_string();
+		if name.starts_with('_') || used.contains(&name) {
+			continue;
+		}
+		// Use the parameter so that rustc doesn't report it a second time.
+		warnings.extend(quote_spanned!(param.span() => let _ = &#param;));
+		if report_unused {
+			let note = format!("#[inline_sql]: parameter `{name}` is not used in the query");
+			warnings.extend(quote_spanned!(param.span() => {
+				#[deprecated(note = #note)]
+				struct UnusedParameter;
+				let _ = UnusedParameter;
+			}));
+		}
+	}
+	warnings
+}
+
+// Get the parameter that a placeholder like `$filter.species` refers to.
+fn placeholder_root(expr: &syn::Expr) -> Option<&syn::Ident> {
+	match expr {
+		syn::Expr::Path(expr) if expr.qself.is_none() => expr.path.get_ident(),
+		syn::Expr::Field(expr) => placeholder_root(&expr.base),
+		syn::Expr::Paren(expr) => placeholder_root(&expr.expr),
+		syn::Expr::Group(expr) => placeholder_root(&expr.expr),
+		_ => None,
+	}
+}
+
+// Get the names bound by a function parameter pattern.
+fn pat_idents<'a>(pat: &'a syn::Pat, output: &mut Vec<&'a syn::Ident>) {
+	match pat {
+		syn::Pat::Ident(pat) => {
+			output.push(&pat.ident);
+			if let Some((_, pat)) = &pat.subpat {
+				pat_idents(pat, output);
+			}
+		},
+		syn::Pat::Paren(pat) => pat_idents(&pat.pat, output),
+		syn::Pat::Reference(pat) => pat_idents(&pat.pat, output),
+		syn::Pat::Type(pat) => pat_idents(&pat.pat, output),
+		syn::Pat::Tuple(pat) => pat.elems.iter().for_each(|pat| pat_idents(pat, output)),
+		syn::Pat::TupleStruct(pat) => pat.elems.iter().for_each(|pat| pat_idents(pat, output)),
+		syn::Pat::Slice(pat) => pat.elems.iter().for_each(|pat| pat_idents(pat, output)),
+		syn::Pat::Struct(pat) => pat.fields.iter().for_each(|field| pat_idents(&field.pat, output)),
+		_ => (),
+	}
+}
+
+fn collect_idents(output: &mut BTreeSet<String>, tokens: TokenStream) {
+	for token in tokens {
+		match token {
+			TokenTree::Ident(ident) => {
+				output.insert(ident.to_string());
+			},
+			TokenTree::Group(group) => collect_idents(output, group.stream()),
+			_ => (),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Get the names of the parameters that are reported as unused.
+	fn unused(warnings: TokenStream) -> Vec<String> {
+		let warnings = warnings.to_string();
+		warnings.split("parameter `")
+			.skip(1)
+			.filter_map(|rest| rest.split_once("` is not used").map(|(name, _)| name.to_string()))
+			.collect()
+	}
+
+	fn check(placeholders: &[syn::Expr], other_uses: TokenStream, allow_unused: bool) -> (Vec<String>, Vec<String>) {
+		let signature: syn::Signature = syn::parse_quote! {
+			fn get_pets(client: &Client, name: &str, filter: &Filter, page: u32, size: u32, unused: i32, _ignored: i32)
+		};
+		let mut errors = Vec::new();
+		let warnings = check_params(&mut errors, &signature, placeholders, '$', other_uses, allow_unused);
+		let errors = errors.iter().map(|e| e.to_string()).collect();
+		(unused(warnings), errors)
+	}
+
+	#[test]
+	fn parameters_used_by_placeholders() {
+		let placeholders = [
+			syn::parse_quote!(name),
+			syn::parse_quote!(filter.species),
+			syn::parse_quote!(page * size),
+		];
+		let (unused, errors) = check(&placeholders, quote::quote!(client), false);
+		assert_eq!(unused, ["unused"]);
+		assert!(errors.is_empty());
+	}
+
+	#[test]
+	fn client_parameter() {
+		let placeholders = [syn::parse_quote!(name), syn::parse_quote!(filter), syn::parse_quote!((page, size, unused))];
+		let (unused, _) = check(&placeholders, quote::quote!(client), false);
+		assert!(unused.is_empty());
+		let (unused, _) = check(&placeholders, TokenStream::new(), false);
+		assert_eq!(unused, ["client"]);
+	}
+
+	#[test]
+	fn allow_unused() {
+		let (unused, errors) = check(&[], TokenStream::new(), true);
+		assert!(unused.is_empty());
+		assert!(errors.is_empty());
+	}
+
+	#[test]
+	fn unknown_placeholders() {
+		// Names starting with an uppercase letter are not checked.
+		let placeholders = [syn::parse_quote!(nmae), syn::parse_quote!(filtr.species), syn::parse_quote!(LIMIT)];
+		let (unused, errors) = check(&placeholders, quote::quote!(client), false);
+		assert_eq!(errors, [
+			"#[inline_sql]: unknown placeholder `$nmae`, did you mean `$name`?",
+			"#[inline_sql]: unknown placeholder `$filtr`, did you mean `$filter`?",
+		]);
+		// Parameters are not reported as unused after an error.
+		assert!(unused.is_empty());
+	}
+
+	#[test]
+	fn suggestion_threshold() {
+		// Names allow one edit per three characters, but at least one edit.
+		let placeholders = [syn::parse_quote!(fltr), syn::parse_quote!(sizes), syn::parse_quote!(size_of), syn::parse_quote!(pge)];
+		let (_, errors) = check(&placeholders, quote::quote!(client), false);
+		assert_eq!(errors, [
+			"#[inline_sql]: unknown placeholder `$fltr`, it does not match any function parameter",
+			"#[inline_sql]: unknown placeholder `$sizes`, did you mean `$size`?",
+			"#[inline_sql]: unknown placeholder `$size_of`, it does not match any function parameter",
+			"#[inline_sql]: unknown placeholder `$pge`, did you mean `$page`?",
+		]);
+	}
+}
@@ -0,0 +1,242 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::input::{QueryType, RowType};
+use crate::util::return_type_ok_span;
+use super::Context;
+
+pub fn expand_body(errors: &mut Vec<syn::Error>, context: Context, query_type: QueryType, asynchronous: bool) -> TokenStream {
+	let no_rows = context.row_count_error("NoRows");
+	let too_many_rows = context.row_count_error("TooManyRows");
+	let Context {
+		signature,
+		client,
+		query,
+		placeholders,
+		map_row,
+		handle_err,
+		..
+	} = &context;
+
+	let await_future = if asynchronous {
+		Some(quote!(.await))
+	} else {
+		None
+	};
+	let krate = if asynchronous {
+		quote!(::tokio_postgres)
+	} else {
+		quote!(::postgres)
+	};
+
+	let try_from_row = |typ: &syn::Type| quote_spanned!(return_type_ok_span(signature) => {
+		#[allow(clippy::useless_conversion)]
+		{
+			let result = <#typ as ::core::convert::TryFrom<#krate::Row>>::try_from(row);
+			#handle_err
+		}
+	});
+	let try_get = |index: usize, typ: &syn::Type| quote_spanned!(typ.span() => {
+		let result = row.try_get::<_, #typ>(#index);
+		#handle_err
+	});
+	let decode_columns = |types: Vec<&syn::Type>| {
+		let expected = types.len();
+		let column_count_error = context.column_count_error(expected, quote!(columns));
+		let elems = types.into_iter().enumerate().map(|(i, typ)| try_get(i, typ));
+		quote_spanned!(return_type_ok_span(signature) => {
+			let columns = row.len();
+			if columns != #expected {
+				#column_count_error
+			}
+			(#(#elems,)*)
+		})
+	};
+
+	let map_elem = |row_type| match (&map_row, row_type) {
+		(Some(map_elem), _) => quote_spanned!(map_elem.span() => {
+			let elem = ::inline_sql::macro_export__::convert_row(#map_elem, row);
+			match elem {
+				Ok(x) => x,
+				Err(e) => {
+					return Err(e)
+				},
+			}
+		}),
+		(None, RowType::Row(typ)) => try_from_row(typ),
+		(None, RowType::Scalar(typ)) => try_get(0, typ),
+		(None, RowType::Tuple(_, elems)) => decode_columns(elems.iter().collect()),
+		(None, RowType::KeyValue { key, value, scalar: true }) => decode_columns(vec![key, value]),
+		(None, RowType::KeyValue { key, value, scalar: false }) => {
+			let key = try_get(0, key);
+			let value = try_from_row(value);
+			quote!({
+				let key = #key;
+				let value = #value;
+				(key, value)
+			})
+		},
+	};
+
+	let map_group = |parent: &syn::Type, child: &syn::Type| match &map_row {
+		Some(map_row) => quote_spanned!(map_row.span() => {
+			let elem = ::inline_sql::macro_export__::convert_row(#map_row, row);
+			match elem {
+				Ok((parent, child)) => (parent, ::core::option::Option::Some(child)),
+				Err(e) => {
+					return Err(e)
+				},
+			}
+		}),
+		None => quote_spanned!(return_type_ok_span(signature) => {
+			let result = <#parent as ::inline_sql::FromRow<#krate::Row>>::from_row(&row);
+			let parent = #handle_err;
+			let result = <::core::option::Option<#child> as ::inline_sql::FromRow<#krate::Row>>::from_row(&row);
+			let child = #handle_err;
+			(parent, child)
+		}),
+	};
+
+	let mut params = TokenStream::new();
+	for placeholder in placeholders {
+		params.extend(quote_spanned!(
+			placeholder.span() => &(#placeholder) as &(dyn #krate::types::ToSql + ::core::marker::Sync),
+		));
+	}
+	let params = quote!(&[#params]);
+
+	// Fetch the rows into `rows`, and get the next row as an `Option<Result<Row, Error>>` with `next_row`.
+	// This lets the async and sync variants share the code that processes the rows.
+	let (fetch_rows, next_row) = if asynchronous {
+		let fetch_rows = quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let params = params.iter().map(|x| *x as &dyn #krate::types::ToSql);
+			let result: ::core::result::Result<#krate::RowStream, #krate::Error> = #client.query_raw(#query, params).await;
+			let stream: #krate::RowStream = #handle_err;
+			let mut rows = ::core::pin::pin!(stream);
+		};
+		(fetch_rows, quote!(rows.next().await))
+	} else {
+		let fetch_rows = quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let params = params.iter().map(|x| *x as &dyn #krate::types::ToSql);
+			let result: ::core::result::Result<#krate::RowIter<'_>, #krate::Error> = #client.query_raw(#query, params);
+			let mut rows: #krate::RowIter<'_> = #handle_err;
+		};
+		(fetch_rows, quote!(::core::result::Result::transpose(#krate::fallible_iterator::FallibleIterator::next(&mut rows))))
+	};
+
+	match query_type {
+		QueryType::Execute => quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let result: ::core::result::Result<u64, #krate::Error> = #client.execute(#query, params)#await_future;
+			let result = #handle_err;
+			Ok(())
+		},
+		QueryType::CountRows => quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let result: ::core::result::Result<u64, #krate::Error> = #client.execute(#query, params)#await_future;
+			let result = #handle_err;
+			Ok(result)
+		},
+		// Synchronous `Collect` queries convert the rows while collecting them into a `Result`,
+		// so an error returned from the closure stops the iteration.
+		QueryType::Collect(row_type) if !asynchronous => {
+			let map_elem = map_elem(row_type);
+			quote! {
+				#fetch_rows
+				let rows = #krate::fallible_iterator::FallibleIterator::iterator(&mut rows);
+				::core::iter::Iterator::collect(::core::iter::Iterator::map(rows, |result| -> ::core::result::Result<#row_type, _> {
+					let row = #handle_err;
+					let elem = #map_elem;
+					::core::result::Result::Ok(elem)
+				}))
+			}
+		},
+		QueryType::List(row_type) | QueryType::Collect(row_type) => {
+			let map_elem = map_elem(row_type);
+			let output = super::collect_output(&query_type);
+			quote! {
+				#fetch_rows
+				let mut output = ::std::vec::Vec::<#row_type>::new();
+				while let ::core::option::Option::Some(result) = #next_row {
+					let row = #handle_err;
+					let elem = #map_elem;
+					output.push(elem);
+				}
+				::core::result::Result::Ok(#output)
+			}
+		},
+		QueryType::Grouped { parent, child, key } => {
+			let map_group = map_group(parent, child);
+			quote! {
+				#fetch_rows
+				let mut output = ::std::vec::Vec::new();
+				while let ::core::option::Option::Some(result) = #next_row {
+					let row = #handle_err;
+					let (parent, child) = #map_group;
+					::inline_sql::macro_export__::push_grouped(&mut output, #key, parent, child);
+				}
+				::core::result::Result::Ok(::inline_sql::macro_export__::finish_grouped(output))
+			}
+		},
+		QueryType::Stream if asynchronous => quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let params = params.iter().map(|x| *x as &dyn #krate::types::ToSql);
+			let result: ::core::result::Result<#krate::RowStream, #krate::Error> = #client.query_raw(#query, params)#await_future;
+			::core::result::Result::Ok(#handle_err)
+		},
+		QueryType::Stream => quote! {
+			let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+			let params = params.iter().map(|x| *x as &dyn #krate::types::ToSql);
+			let result: ::core::result::Result<#krate::RowIter<'_>, #krate::Error> = #client.query_raw(#query, params);
+			::core::result::Result::Ok(#handle_err)
+		},
+		QueryType::MappedStream { row, error, boxed } if asynchronous => {
+			let map_elem = map_elem(row);
+			let stream = context.map_stream(quote!(stream), row, error, boxed, map_elem);
+			quote! {
+				let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+				let params = params.iter().map(|x| *x as &dyn #krate::types::ToSql);
+				let result: ::core::result::Result<#krate::RowStream, #krate::Error> = #client.query_raw(#query, params)#await_future;
+				let stream: #krate::RowStream = #handle_err;
+				::core::result::Result::Ok(#stream)
+			}
+		},
+		QueryType::MappedStream { .. } => {
+			errors.push(syn::Error::new(return_type_ok_span(signature), "#[inline_sql]: returning a mapped stream requires an `async` function"));
+			quote!(::core::unreachable!())
+		},
+		QueryType::Optional(row_type) => {
+			let map_elem = map_elem(row_type);
+			quote! {
+				let params: &[&(dyn #krate::types::ToSql + ::core::marker::Sync)] = #params;
+				let result: ::core::result::Result<::core::option::Option<#krate::Row>, #krate::Error> = #client.query_opt(#query, params)#await_future;
+				match #handle_err {
+					::core::option::Option::None => ::core::result::Result::Ok(::core::option::Option::None),
+					::core::option::Option::Some(row) => {
+						let elem = #map_elem;
+						::core::result::Result::Ok(::core::option::Option::Some(elem))
+					},
+				}
+			}
+		},
+		QueryType::One(row_type) => {
+			let map_elem = map_elem(row_type);
+			quote! {
+				#fetch_rows
+				let row = match #next_row {
+					::core::option::Option::Some(result) => #handle_err,
+					::core::option::Option::None => #no_rows,
+				};
+				if let ::core::option::Option::Some(result) = #next_row {
+					let _ = #handle_err;
+					#too_many_rows
+				}
+				let elem = #map_elem;
+				::core::result::Result::Ok(elem)
+			}
+		},
+	}
+}
@@ -0,0 +1,199 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::input::{QueryType, RowType};
+use crate::util::return_type_ok_span;
+use super::Context;
+
+pub fn expand_body(errors: &mut Vec<syn::Error>, context: Context, query_type: QueryType) -> TokenStream {
+	let no_rows = context.row_count_error("NoRows");
+	let too_many_rows = context.row_count_error("TooManyRows");
+	let Context {
+		signature,
+		client,
+		query,
+		placeholders,
+		map_row,
+		handle_err,
+		..
+	} = &context;
+
+	// Row conversion happens inside the closure passed to `query_map()` and `query_row()`,
+	// so we can not return from the function there.
+	// Instead, the closure gives back the conversion result, and we handle the error afterwards.
+	let try_from_row = |typ: &syn::Type| quote_spanned!(return_type_ok_span(signature) => {
+		#[allow(clippy::useless_conversion)]
+		<#typ as ::core::convert::TryFrom<&::rusqlite::Row<'_>>>::try_from(row)
+	});
+	// The `?` operator returns from the closure passed to `query_map()`, which also gives a `rusqlite::Error`.
+	let decode_columns = |types: Vec<&syn::Type>| {
+		let elems = types.into_iter().enumerate().map(|(i, typ)| quote_spanned!(typ.span() => row.get::<_, #typ>(#i)?));
+		quote_spanned!(return_type_ok_span(signature) => {
+			::core::result::Result::Ok::<_, ::rusqlite::Error>((#(#elems,)*))
+		})
+	};
+
+	let convert_row = |row_type| match (&map_row, row_type) {
+		(Some(map_row), _) => quote_spanned!(map_row.span() => {
+			::inline_sql::macro_export__::convert_row(#map_row, row)
+		}),
+		(None, RowType::Row(typ)) => try_from_row(typ),
+		(None, RowType::Scalar(typ)) => quote_spanned!(return_type_ok_span(signature) => {
+			row.get::<_, #typ>(0)
+		}),
+		(None, RowType::Tuple(_, elems)) => decode_columns(elems.iter().collect()),
+		(None, RowType::KeyValue { key, value, scalar: true }) => decode_columns(vec![key, value]),
+		(None, RowType::KeyValue { key, value, scalar: false }) => {
+			let value = try_from_row(value);
+			quote_spanned!(key.span() => {
+				let key = row.get::<_, #key>(0)?;
+				let value = #value;
+				value.map(|value| (key, value))
+			})
+		},
+	};
+
+	let convert_group = |parent: &syn::Type, child: &syn::Type| match &map_row {
+		Some(map_row) => quote_spanned!(map_row.span() => {
+			::inline_sql::macro_export__::convert_row(#map_row, row)
+				.map(|(parent, child)| (parent, ::core::option::Option::Some(child)))
+		}),
+		None => quote_spanned!(return_type_ok_span(signature) => {
+			<#parent as ::inline_sql::FromRow<::rusqlite::Row<'_>>>::from_row(row).and_then(|parent| {
+				let child = <::core::option::Option<#child> as ::inline_sql::FromRow<::rusqlite::Row<'_>>>::from_row(row)?;
+				::core::result::Result::Ok((parent, child))
+			})
+		}),
+	};
+
+	// The number of columns is known after preparing the statement, so check it once for tuples.
+	let check_columns = |row_type| {
+		let expected = match (&map_row, row_type) {
+			(None, RowType::Tuple(_, elems)) => elems.len(),
+			(None, RowType::KeyValue { scalar: true, .. }) => 2,
+			_ => return quote!(),
+		};
+		let column_count_error = context.column_count_error(expected, quote!(columns));
+		quote! {
+			let columns = statement.column_count();
+			if columns != #expected {
+				#column_count_error
+			}
+		}
+	};
+	let handle_convert_err = match &map_row {
+		Some(map_row) => quote_spanned!(map_row.span() => {
+			match result {
+				Ok(x) => x,
+				Err(e) => return Err(e),
+			}
+		}),
+		None => handle_err.clone(),
+	};
+
+	let mut params = TokenStream::new();
+	for placeholder in placeholders {
+		params.extend(quote_spanned!(
+			placeholder.span() => &(#placeholder) as &dyn ::rusqlite::ToSql,
+		));
+	}
+	let params = quote!(&[#params]);
+
+	let prepare = quote! {
+		let params: &[&dyn ::rusqlite::ToSql] = #params;
+		let result: ::core::result::Result<::rusqlite::CachedStatement<'_>, ::rusqlite::Error> = #client.prepare_cached(#query);
+		let mut statement = #handle_err;
+	};
+
+	let output = super::collect_output(&query_type);
+
+	match query_type {
+		QueryType::Execute => quote! {
+			#prepare
+			let result: ::core::result::Result<usize, ::rusqlite::Error> = statement.execute(params);
+			let result = #handle_err;
+			Ok(())
+		},
+		QueryType::CountRows => quote! {
+			#prepare
+			let result: ::core::result::Result<usize, ::rusqlite::Error> = statement.execute(params);
+			let result = #handle_err;
+			Ok(result as u64)
+		},
+		QueryType::List(row_type) | QueryType::Collect(row_type) => {
+			let check_columns = check_columns(row_type);
+			let convert_row = convert_row(row_type);
+			quote! {
+				#prepare
+				#check_columns
+				let result = statement.query_map(params, |row| ::core::result::Result::Ok(#convert_row));
+				let rows = #handle_err;
+				let mut output = ::std::vec::Vec::<#row_type>::new();
+				for result in rows {
+					let result = #handle_err;
+					let elem = #handle_convert_err;
+					output.push(elem);
+				}
+				::core::result::Result::Ok(#output)
+			}
+		},
+		QueryType::Grouped { parent, child, key } => {
+			let convert_group = convert_group(parent, child);
+			quote! {
+				#prepare
+				let result = statement.query_map(params, |row| ::core::result::Result::Ok(#convert_group));
+				let rows = #handle_err;
+				let mut output = ::std::vec::Vec::new();
+				for result in rows {
+					let result = #handle_err;
+					let (parent, child) = #handle_convert_err;
+					::inline_sql::macro_export__::push_grouped(&mut output, #key, parent, child);
+				}
+				::core::result::Result::Ok(::inline_sql::macro_export__::finish_grouped(output))
+			}
+		},
+		QueryType::Optional(row_type) => {
+			let check_columns = check_columns(row_type);
+			let convert_row = convert_row(row_type);
+			quote! {
+				#prepare
+				#check_columns
+				let result = statement.query_map(params, |row| ::core::result::Result::Ok(#convert_row));
+				let mut rows = #handle_err;
+				match rows.next() {
+					::core::option::Option::None => ::core::result::Result::Ok(::core::option::Option::None),
+					::core::option::Option::Some(result) => {
+						let result = #handle_err;
+						let elem = #handle_convert_err;
+						::core::result::Result::Ok(::core::option::Option::Some(elem))
+					},
+				}
+			}
+		},
+		QueryType::One(row_type) => {
+			let check_columns = check_columns(row_type);
+			let convert_row = convert_row(row_type);
+			quote! {
+				#prepare
+				#check_columns
+				let result = statement.query_map(params, |row| ::core::result::Result::Ok(#convert_row));
+				let mut rows = #handle_err;
+				let result = match rows.next() {
+					::core::option::Option::Some(result) => #handle_err,
+					::core::option::Option::None => #no_rows,
+				};
+				if let ::core::option::Option::Some(result) = rows.next() {
+					let _ = #handle_err;
+					#too_many_rows
+				}
+				let elem = #handle_convert_err;
+				::core::result::Result::Ok(elem)
+			}
+		},
+		QueryType::Stream | QueryType::MappedStream { .. } => {
+			errors.push(syn::Error::new(return_type_ok_span(signature), "#[inline_sql]: the `rusqlite` backend does not support returning a row stream"));
+			quote!(::core::unreachable!())
+		},
+	}
+}
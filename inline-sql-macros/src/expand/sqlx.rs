@@ -0,0 +1,203 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+use crate::input::{QueryType, RowType};
+use crate::util::return_type_ok_span;
+use super::Context;
+
+pub fn expand_body(errors: &mut Vec<syn::Error>, context: Context, query_type: QueryType) -> TokenStream {
+	let no_rows = context.row_count_error("NoRows");
+	let too_many_rows = context.row_count_error("TooManyRows");
+	let Context {
+		signature,
+		client,
+		query,
+		placeholders,
+		map_row,
+		handle_err,
+		..
+	} = &context;
+
+	let try_get = |index: usize, typ: &syn::Type| quote_spanned!(typ.span() => {
+		let result = ::sqlx::Row::try_get::<#typ, _>(&row, #index);
+		#handle_err
+	});
+	let decode_columns = |types: Vec<&syn::Type>| {
+		let expected = types.len();
+		let column_count_error = context.column_count_error(expected, quote!(columns));
+		let elems = types.into_iter().enumerate().map(|(i, typ)| try_get(i, typ));
+		quote_spanned!(return_type_ok_span(signature) => {
+			let columns = ::sqlx::Row::len(&row);
+			if columns != #expected {
+				#column_count_error
+			}
+			(#(#elems,)*)
+		})
+	};
+
+	let map_elem = |row_type| match (&map_row, row_type) {
+		(Some(map_elem), _) => quote_spanned!(map_elem.span() => {
+			let elem = ::inline_sql::macro_export__::convert_row(#map_elem, row);
+			match elem {
+				Ok(x) => x,
+				Err(e) => {
+					return Err(e)
+				},
+			}
+		}),
+		(None, RowType::Tuple(_, elems)) => decode_columns(elems.iter().collect()),
+		(None, RowType::KeyValue { key, value, scalar: true }) => decode_columns(vec![key, value]),
+		(None, RowType::KeyValue { key, value, scalar: false }) => {
+			let key = try_get(0, key);
+			quote_spanned!(return_type_ok_span(signature) => {
+				let key = #key;
+				let result = <#value as ::sqlx::FromRow<'_, _>>::from_row(&row);
+				let value = #handle_err;
+				(key, value)
+			})
+		},
+		(None, RowType::Row(_) | RowType::Scalar(_)) => quote!(row),
+	};
+
+	// Use `query_as()` to decode rows with `sqlx::FromRow` or `query_scalar()` to decode the first column,
+	// unless a custom `map_row` function is given or the rows are decoded as tuples or key-value pairs.
+	let sqlx_query = |row_type| match (&map_row, row_type) {
+		(None, Some(RowType::Row(typ))) => quote_spanned!(return_type_ok_span(signature) => ::sqlx::query_as::<_, #typ>(#query)),
+		(None, Some(RowType::Scalar(typ))) => quote_spanned!(return_type_ok_span(signature) => ::sqlx::query_scalar::<_, #typ>(#query)),
+		_ => quote!(::sqlx::query(#query)),
+	};
+
+	// Borrow the parameters in a local variable first, so temporaries live until the query is finished.
+	let params = quote!(let params = (#(&(#placeholders),)*););
+	let mut binds = TokenStream::new();
+	for (i, placeholder) in placeholders.iter().enumerate() {
+		let index = syn::Index::from(i);
+		binds.extend(quote_spanned!(placeholder.span() => .bind(params.#index)));
+	}
+
+	// Returned streams outlive the function body, so the parameters are cloned and bound by value.
+	// This also works for fields of borrowed parameters, like `$filter.species`.
+	let mut binds_by_value = TokenStream::new();
+	for placeholder in placeholders {
+		binds_by_value.extend(quote_spanned!(placeholder.span() => .bind(::core::clone::Clone::clone(&(#placeholder)))));
+	}
+
+	let output = super::collect_output(&query_type);
+
+	match query_type {
+		QueryType::Execute => {
+			let sqlx_query = sqlx_query(None);
+			quote! {
+				#params
+				let result = #sqlx_query #binds.execute(#client).await;
+				let result = #handle_err;
+				Ok(())
+			}
+		},
+		QueryType::CountRows => {
+			let sqlx_query = sqlx_query(None);
+			quote! {
+				#params
+				let result = #sqlx_query #binds.execute(#client).await;
+				let result = #handle_err;
+				Ok(result.rows_affected())
+			}
+		},
+		QueryType::List(row_type) | QueryType::Collect(row_type) => {
+			let map_elem = map_elem(row_type);
+			let sqlx_query = sqlx_query(Some(row_type));
+			quote! {
+				#params
+				let stream = #sqlx_query #binds.fetch(#client);
+				let mut stream = ::core::pin::pin!(stream);
+				let mut output = ::std::vec::Vec::<#row_type>::new();
+				while let ::core::option::Option::Some(result) = stream.next().await {
+					let row = #handle_err;
+					let elem = #map_elem;
+					output.push(elem);
+				}
+				::core::result::Result::Ok(#output)
+			}
+		},
+		QueryType::Grouped { key, .. } => {
+			let map_group = match &map_row {
+				Some(map_row) => quote_spanned!(map_row.span() => {
+					let elem = ::inline_sql::macro_export__::convert_row(#map_row, row);
+					match elem {
+						Ok((parent, child)) => (parent, ::core::option::Option::Some(child)),
+						Err(e) => {
+							return Err(e)
+						},
+					}
+				}),
+				None => {
+					let message = "#[inline_sql]: the `sqlx` backend can only use `group_by` together with `map_row`, since `sqlx::FromRow` can not decode an optional child";
+					errors.push(syn::Error::new(return_type_ok_span(signature), message));
+					return quote!(::core::unreachable!());
+				},
+			};
+			let sqlx_query = sqlx_query(None);
+			quote! {
+				#params
+				let stream = #sqlx_query #binds.fetch(#client);
+				let mut stream = ::core::pin::pin!(stream);
+				let mut output = ::std::vec::Vec::new();
+				while let ::core::option::Option::Some(result) = stream.next().await {
+					let row = #handle_err;
+					let (parent, child) = #map_group;
+					::inline_sql::macro_export__::push_grouped(&mut output, #key, parent, child);
+				}
+				::core::result::Result::Ok(::inline_sql::macro_export__::finish_grouped(output))
+			}
+		},
+		QueryType::Optional(row_type) => {
+			let map_elem = map_elem(row_type);
+			let sqlx_query = sqlx_query(Some(row_type));
+			quote! {
+				#params
+				let result = #sqlx_query #binds.fetch_optional(#client).await;
+				match #handle_err {
+					::core::option::Option::None => ::core::result::Result::Ok(::core::option::Option::None),
+					::core::option::Option::Some(row) => {
+						let elem = #map_elem;
+						::core::result::Result::Ok(::core::option::Option::Some(elem))
+					},
+				}
+			}
+		},
+		QueryType::One(row_type) => {
+			let map_elem = map_elem(row_type);
+			let sqlx_query = sqlx_query(Some(row_type));
+			quote! {
+				#params
+				let stream = #sqlx_query #binds.fetch(#client);
+				let mut stream = ::core::pin::pin!(stream);
+				let row = match stream.next().await {
+					::core::option::Option::Some(result) => #handle_err,
+					::core::option::Option::None => #no_rows,
+				};
+				if let ::core::option::Option::Some(result) = stream.next().await {
+					let _ = #handle_err;
+					#too_many_rows
+				}
+				let elem = #map_elem;
+				::core::result::Result::Ok(elem)
+			}
+		},
+		QueryType::Stream => {
+			let sqlx_query = sqlx_query(None);
+			quote! {
+				::core::result::Result::Ok(#sqlx_query #binds_by_value.fetch(#client))
+			}
+		},
+		QueryType::MappedStream { row, error, boxed } => {
+			let map_elem = map_elem(row);
+			let sqlx_query = sqlx_query(Some(row));
+			let stream = context.map_stream(quote!(#sqlx_query #binds_by_value.fetch(#client)), row, error, boxed, map_elem);
+			quote! {
+				::core::result::Result::Ok(#stream)
+			}
+		},
+	}
+}
@@ -1,10 +1,22 @@
 use proc_macro2::{TokenStream, TokenTree, Ident, Span};
 
-#[derive(Default)]
+use super::{Backend, QueryKind};
+
+#[derive(Clone, Default)]
 pub struct Arguments {
 	pub client: Option<syn::Expr>,
 	pub map_row: Option<syn::Expr>,
 	pub map_err: Option<syn::Expr>,
+	pub group_by: Option<syn::Expr>,
+	pub backend: Option<Backend>,
+	pub kind: Option<QueryKind>,
+	pub row: Option<syn::Type>,
+	pub scalar: bool,
+	pub file: Option<syn::LitStr>,
+	pub name: Option<syn::LitStr>,
+	pub placeholder: Option<syn::LitChar>,
+	pub allow_unused: bool,
+	pub impl_for: Option<syn::Type>,
 }
 
 impl Arguments {
@@ -12,17 +24,102 @@ impl Arguments {
 		Default::default()
 	}
 
+	// Remove the `#[inline_sql(...)]` attribute from an item inside a larger item, and parse its arguments.
+	pub fn take_from_attributes(errors: &mut Vec<syn::Error>, attributes: &mut Vec<syn::Attribute>) -> Option<Self> {
+		let mut output = None;
+		attributes.retain(|attribute| {
+			let is_inline_sql = attribute.path().segments.last().is_some_and(|last| last.ident == "inline_sql");
+			if !is_inline_sql {
+				return true;
+			}
+			if output.is_some() {
+				errors.push(syn::Error::new_spanned(attribute, "#[inline_sql]: duplicate #[inline_sql] attribute"));
+				return false;
+			}
+			let mut args = Self::new();
+			match &attribute.meta {
+				syn::Meta::Path(_) => (),
+				syn::Meta::List(list) => args.parse_params(errors, list.tokens.clone(), Some(list.delimiter.span().close())),
+				syn::Meta::NameValue(meta) => errors.push(syn::Error::new_spanned(meta.eq_token, "#[inline_sql]: expected arguments in parentheses")),
+			}
+			output = Some(args);
+			false
+		});
+		output
+	}
+
+	// Use the arguments of an outer item, like an `impl` block, where they are not given here.
+	//
+	// Only the arguments that make sense for all methods can be given on the outer item, see `parse_outer_params()`.
+	// The `impl_for` argument only applies to the outer item itself, so it is not inherited.
+	pub fn inherit(&mut self, outer: &Self) {
+		fn inherit<T: Clone>(store_at: &mut Option<T>, outer: &Option<T>) {
+			if store_at.is_none() {
+				*store_at = outer.clone();
+			}
+		}
+		inherit(&mut self.client, &outer.client);
+		inherit(&mut self.map_err, &outer.map_err);
+		inherit(&mut self.backend, &outer.backend);
+	}
+
+	// Parse the arguments of an outer item, like an `impl` block or a trait.
+	//
+	// Arguments that describe a single query are rejected, since they can not apply to every method.
+	pub fn parse_outer_params(&mut self, errors: &mut Vec<syn::Error>, tokens: TokenStream, item: &str) {
+		const PER_FUNCTION: &[&str] = &["map_row", "group_by", "kind", "row", "scalar", "file", "name", "placeholder", "allow_unused"];
+		for arg in split_args(errors, tokens, None) {
+			if PER_FUNCTION.iter().any(|name| arg.ident == name) {
+				errors.push(syn::Error::new_spanned(&arg.ident, format!(
+					"#[inline_sql]: the `{}` argument can not be used on {item}, only on its functions",
+					arg.ident,
+				)));
+			} else {
+				self.parse_arg(errors, arg);
+			}
+		}
+	}
+
 	pub fn parse_params(&mut self, errors: &mut Vec<syn::Error>, tokens: TokenStream, backup_error_span: Option<Span>) {
 		for arg in split_args(errors, tokens, backup_error_span) {
-			if arg.ident == "client" {
-				set_once(&mut self.client, arg, errors);
-			} else if arg.ident == "map_row" {
-				set_once(&mut self.map_row, arg, errors);
-			} else if arg.ident == "map_err" {
-				set_once(&mut self.map_err, arg, errors);
-			} else {
-				errors.push(syn::Error::new_spanned(&arg.ident, "#[inline_sql]: unrecognized argument, expected one of `client`, `map_row` or `map_err`"));
+			self.parse_arg(errors, arg);
+		}
+	}
+
+	fn parse_arg(&mut self, errors: &mut Vec<syn::Error>, arg: Arg) {
+		if arg.ident == "client" {
+			set_once(&mut self.client, arg, errors);
+		} else if arg.ident == "map_row" {
+			set_once(&mut self.map_row, arg, errors);
+		} else if arg.ident == "map_err" {
+			set_once(&mut self.map_err, arg, errors);
+		} else if arg.ident == "group_by" {
+			set_once(&mut self.group_by, arg, errors);
+		} else if arg.ident == "backend" {
+			let is_first = self.backend.is_none();
+			let span = arg.value.as_ref().map_or_else(|| arg.ident.span(), syn::spanned::Spanned::span);
+			set_once(&mut self.backend, arg, errors);
+			if let (true, Some(backend)) = (is_first, self.backend) {
+				backend.check_enabled(errors, span);
 			}
+		} else if arg.ident == "kind" {
+			set_once(&mut self.kind, arg, errors);
+		} else if arg.ident == "row" {
+			set_once(&mut self.row, arg, errors);
+		} else if arg.ident == "scalar" {
+			set_flag(&mut self.scalar, arg, errors);
+		} else if arg.ident == "file" {
+			set_once(&mut self.file, arg, errors);
+		} else if arg.ident == "name" {
+			set_once(&mut self.name, arg, errors);
+		} else if arg.ident == "placeholder" {
+			set_once(&mut self.placeholder, arg, errors);
+		} else if arg.ident == "allow_unused" {
+			set_flag(&mut self.allow_unused, arg, errors);
+		} else if arg.ident == "impl_for" {
+			set_once(&mut self.impl_for, arg, errors);
+		} else {
+			errors.push(syn::Error::new_spanned(&arg.ident, "#[inline_sql]: unrecognized argument, expected one of `client`, `map_row`, `map_err`, `group_by`, `backend`, `kind`, `row`, `scalar`, `file`, `name`, `placeholder`, `allow_unused` or `impl_for`"));
 		}
 	}
 }
@@ -32,15 +129,34 @@ fn set_once<T: syn::parse::Parse>(store_at: &mut Option<T>, arg: Arg, errors: &m
 		errors.push(syn::Error::new_spanned(&arg.ident, format!("[inline_sql]: duplicate {} argument", arg.ident)));
 		return;
 	}
-	match syn::parse2(arg.value) {
+	let value = match arg.value {
+		Some(x) => x,
+		None => {
+			errors.push(syn::Error::new_spanned(&arg.ident, format!("#[inline_sql]: expected a value for the {} argument", arg.ident)));
+			return;
+		},
+	};
+	match syn::parse2(value) {
 		Err(e) => errors.push(e),
 		Ok(value) => *store_at = Some(value),
 	}
 }
 
+fn set_flag(store_at: &mut bool, arg: Arg, errors: &mut Vec<syn::Error>) {
+	if *store_at {
+		errors.push(syn::Error::new_spanned(&arg.ident, format!("[inline_sql]: duplicate {} argument", arg.ident)));
+		return;
+	}
+	if let Some(value) = arg.value {
+		errors.push(syn::Error::new_spanned(value, format!("#[inline_sql]: the {} argument does not take a value", arg.ident)));
+		return;
+	}
+	*store_at = true;
+}
+
 struct Arg {
 	ident: proc_macro2::Ident,
-	value: proc_macro2::TokenStream,
+	value: Option<proc_macro2::TokenStream>,
 }
 
 fn split_args(errors: &mut Vec<syn::Error>, tokens: TokenStream, backup_error_span: Option<Span>) -> Vec<Arg> {
@@ -76,14 +192,17 @@ impl Arg {
 	fn parse(tokens: TokenStream, backup_error_span: Option<Span>) -> Result<Arg, syn::Error> {
 		let mut tokens = tokens.into_iter();
 		let ident = expect_identifier(tokens.next(), backup_error_span.unwrap_or(Span::call_site()))?;
-		let eq = expect_punct(tokens.next(), '=', backup_error_span.unwrap_or(ident.span()))?;
+		let eq = match tokens.next() {
+			None => return Ok(Arg { ident, value: None }),
+			token => expect_punct(token, '=', backup_error_span.unwrap_or(ident.span()))?,
+		};
 		let value: TokenStream = tokens.collect();
 		if value.is_empty() {
 			Err(syn::Error::new(backup_error_span.unwrap_or(eq.span()), "expected a value"))
 		} else {
 			Ok(Arg {
 				ident,
-				value,
+				value: Some(value),
 			})
 		}
 	}
@@ -0,0 +1,79 @@
+use crate::input::PlaceholderStyle;
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Backend {
+	TokioPostgres,
+	Postgres,
+	Rusqlite,
+	Sqlx,
+}
+
+impl Backend {
+	pub fn default_for(signature: &syn::Signature) -> Self {
+		// The default does not depend on the enabled features, so enabling a feature never changes the generated code.
+		if signature.asyncness.is_some() {
+			Self::TokioPostgres
+		} else {
+			Self::Postgres
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::TokioPostgres => "tokio_postgres",
+			Self::Postgres => "postgres",
+			Self::Rusqlite => "rusqlite",
+			Self::Sqlx => "sqlx",
+		}
+	}
+
+	pub fn is_async(self) -> bool {
+		match self {
+			Self::TokioPostgres => true,
+			Self::Postgres => false,
+			Self::Rusqlite => false,
+			Self::Sqlx => true,
+		}
+	}
+
+	// The `rusqlite` and `sqlx` backends are opt-in, so they must be enabled with the feature of the same name.
+	pub fn check_enabled(self, errors: &mut Vec<syn::Error>, span: proc_macro2::Span) {
+		let enabled = match self {
+			Self::TokioPostgres | Self::Postgres => true,
+			Self::Rusqlite => cfg!(feature = "rusqlite"),
+			Self::Sqlx => cfg!(feature = "sqlx"),
+		};
+		if !enabled {
+			errors.push(syn::Error::new(span, format!(
+				"#[inline_sql]: the `{name}` backend requires the `{name}` feature of `inline-sql`",
+				name = self.name(),
+			)));
+		}
+	}
+
+	pub fn placeholder_style(self) -> PlaceholderStyle {
+		match self {
+			Self::TokioPostgres => PlaceholderStyle::Dollar,
+			Self::Postgres => PlaceholderStyle::Dollar,
+			Self::Rusqlite => PlaceholderStyle::QuestionMark,
+			Self::Sqlx => PlaceholderStyle::Dollar,
+		}
+	}
+}
+
+impl syn::parse::Parse for Backend {
+	fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+		let ident: syn::Ident = input.parse()?;
+		if ident == "tokio_postgres" {
+			Ok(Self::TokioPostgres)
+		} else if ident == "postgres" {
+			Ok(Self::Postgres)
+		} else if ident == "rusqlite" {
+			Ok(Self::Rusqlite)
+		} else if ident == "sqlx" {
+			Ok(Self::Sqlx)
+		} else {
+			Err(syn::Error::new_spanned(ident, "#[inline_sql]: unknown backend, expected one of `tokio_postgres`, `postgres`, `rusqlite` or `sqlx`"))
+		}
+	}
+}
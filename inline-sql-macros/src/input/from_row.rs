@@ -0,0 +1,195 @@
+use syn::ext::IdentExt;
+
+use super::Backend;
+
+pub struct FromRowStruct {
+	pub ident: syn::Ident,
+	pub generics: syn::Generics,
+	pub backends: Vec<RowBackend>,
+	pub fields: Vec<FromRowField>,
+}
+
+// The backends that the row conversion is implemented for.
+//
+// The `tokio_postgres` and `postgres` backends share the same row type.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum RowBackend {
+	Postgres,
+	Rusqlite,
+}
+
+pub struct FromRowField {
+	pub member: syn::Member,
+	pub typ: syn::Type,
+	pub column: Column,
+	pub default: bool,
+	pub decode: Decode,
+}
+
+// The column of a field: by name for structs with named fields, or by position for tuple structs.
+pub enum Column {
+	Name(syn::LitStr),
+	Index(usize),
+}
+
+pub enum Decode {
+	// Decode the column as the field type.
+	Column,
+
+	// Decode the column as another type, and convert it with `TryFrom`.
+	TryFrom(Box<syn::Type>),
+
+	// Convert the whole row into the field type, optionally reading columns with a prefix.
+	Flatten(Option<syn::LitStr>),
+
+	// Do not decode the field, but use `Default::default()`.
+	Skip,
+}
+
+impl FromRowStruct {
+	pub fn from_derive_input(errors: &mut Vec<syn::Error>, input: syn::DeriveInput) -> Result<Self, syn::Error> {
+		let data = match input.data {
+			syn::Data::Struct(data) => data,
+			syn::Data::Enum(data) => return Err(syn::Error::new_spanned(data.enum_token, "#[derive(FromRow)]: can only be derived for structs")),
+			syn::Data::Union(data) => return Err(syn::Error::new_spanned(data.union_token, "#[derive(FromRow)]: can only be derived for structs")),
+		};
+
+		let mut backends = Vec::new();
+		for attribute in input.attrs.iter().filter(|attribute| attribute.path().is_ident("sql")) {
+			let result = attribute.parse_nested_meta(|meta| {
+				if meta.path.is_ident("backend") {
+					let value = meta.value()?;
+					let span = value.span();
+					let backend: Backend = value.parse()?;
+					let backend = match backend {
+						Backend::TokioPostgres | Backend::Postgres => RowBackend::Postgres,
+						Backend::Rusqlite => RowBackend::Rusqlite,
+						Backend::Sqlx => return Err(meta.error("#[derive(FromRow)]: the `sqlx` backend is not supported, use `#[derive(sqlx::FromRow)]` instead")),
+					};
+					if !backends.contains(&backend) {
+						backend.check_enabled(errors, span);
+						backends.push(backend);
+					}
+					Ok(())
+				} else {
+					Err(meta.error("#[derive(FromRow)]: unrecognized attribute, expected `backend`"))
+				}
+			});
+			if let Err(e) = result {
+				errors.push(e);
+			}
+		}
+		if backends.is_empty() {
+			RowBackend::Postgres.check_enabled(errors, input.ident.span());
+			backends.push(RowBackend::Postgres);
+		}
+
+		let fields = data.fields.into_iter()
+			.enumerate()
+			.map(|(index, field)| FromRowField::from_field(errors, index, field))
+			.collect();
+
+		Ok(Self {
+			ident: input.ident,
+			generics: input.generics,
+			backends,
+			fields,
+		})
+	}
+}
+
+impl RowBackend {
+	// The `postgres` family relies on runtime code of `inline-sql` that is only available with one of the features,
+	// and `rusqlite` must be enabled explicitly, like for `#[inline_sql]`.
+	fn check_enabled(self, errors: &mut Vec<syn::Error>, span: proc_macro2::Span) {
+		match self {
+			Self::Postgres if !cfg!(feature = "tokio-postgres") && !cfg!(feature = "postgres") => errors.push(syn::Error::new(
+				span,
+				"#[derive(FromRow)]: the `postgres` backend requires the `tokio-postgres` or `postgres` feature of `inline-sql`, use `#[sql(backend = rusqlite)]` to select another backend",
+			)),
+			Self::Rusqlite if !cfg!(feature = "rusqlite") => errors.push(syn::Error::new(
+				span,
+				"#[derive(FromRow)]: the `rusqlite` backend requires the `rusqlite` feature of `inline-sql`",
+			)),
+			_ => (),
+		}
+	}
+}
+
+impl FromRowField {
+	fn from_field(errors: &mut Vec<syn::Error>, index: usize, field: syn::Field) -> Self {
+		let mut rename = None;
+		let mut default = false;
+		let mut decode = Decode::Column;
+		let mut prefix = None;
+		for attribute in field.attrs.iter().filter(|attribute| attribute.path().is_ident("sql")) {
+			let result = attribute.parse_nested_meta(|meta| {
+				if meta.path.is_ident("rename") {
+					rename = Some(meta.value()?.parse::<syn::LitStr>()?);
+				} else if meta.path.is_ident("default") {
+					default = true;
+				} else if meta.path.is_ident("flatten") {
+					decode = Decode::Flatten(None);
+				} else if meta.path.is_ident("prefix") {
+					prefix = Some(meta.value()?.parse::<syn::LitStr>()?);
+				} else if meta.path.is_ident("skip") {
+					decode = Decode::Skip;
+				} else if meta.path.is_ident("try_from") {
+					decode = Decode::TryFrom(meta.value()?.parse()?);
+				} else {
+					return Err(meta.error("#[derive(FromRow)]: unrecognized attribute, expected one of `rename`, `default`, `flatten`, `prefix`, `skip` or `try_from`"));
+				}
+				Ok(())
+			});
+			if let Err(e) = result {
+				errors.push(e);
+			}
+		}
+
+		let renamed = rename.is_some();
+		let column = match (&field.ident, rename) {
+			(_, Some(rename)) => Column::Name(rename),
+			(Some(ident), None) => Column::Name(syn::LitStr::new(&ident.unraw().to_string(), ident.span())),
+			(None, None) => Column::Index(index),
+		};
+		let member = match field.ident {
+			Some(ident) => syn::Member::Named(ident),
+			None => syn::Member::Unnamed(index.into()),
+		};
+
+		// The `prefix` attribute implies `flatten`.
+		if let Some(prefix) = prefix {
+			match decode {
+				Decode::Column | Decode::Flatten(_) => decode = Decode::Flatten(Some(prefix)),
+				_ => errors.push(syn::Error::new_spanned(prefix, "#[derive(FromRow)]: the `prefix` attribute can only be used on a flattened field")),
+			}
+		}
+
+		match decode {
+			Decode::Flatten(_) if renamed => {
+				errors.push(syn::Error::new_spanned(&member, "#[derive(FromRow)]: the `rename` attribute can not be used on a flattened field"));
+			},
+			Decode::Skip if default => {
+				errors.push(syn::Error::new_spanned(&member, "#[derive(FromRow)]: the `default` attribute can not be used on a skipped field"));
+			},
+			_ => (),
+		}
+
+		Self {
+			member,
+			typ: field.ty,
+			column,
+			default,
+			decode,
+		}
+	}
+}
+
+impl quote::ToTokens for Column {
+	fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+		match self {
+			Self::Name(name) => name.to_tokens(tokens),
+			Self::Index(index) => index.to_tokens(tokens),
+		}
+	}
+}
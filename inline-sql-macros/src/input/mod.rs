@@ -1,21 +1,38 @@
 mod args;
+mod backend;
+mod from_row;
+mod sql_file;
 mod sql_function;
 mod query;
 
 pub use args::Arguments;
-pub use sql_function::{SqlFunction, QueryType};
-pub use query::{Query, QueryMacro};
+pub use backend::Backend;
+pub use from_row::{Column, Decode, FromRowField, FromRowStruct, RowBackend};
+pub use sql_file::SqlFile;
+pub use sql_function::{SqlFunction, QueryKind, QueryType, RowType};
+pub use query::{PlaceholderStyle, Query, QueryMacro};
 
+#[allow(clippy::large_enum_variant)]
 pub enum Item {
 	Function(SqlFunction),
+	Impl(syn::ItemImpl),
+	Trait(syn::ItemTrait),
 }
 
 impl syn::parse::Parse for Item {
 	fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
 		if SqlFunction::peek(input) {
-			Ok(Self::Function(input.parse()?))
+			return Ok(Self::Function(input.parse()?));
+		}
+		let fork = input.fork();
+		fork.call(syn::Attribute::parse_outer)?;
+		fork.parse::<syn::Visibility>()?;
+		if fork.peek(syn::token::Impl) || fork.peek(syn::token::Unsafe) && fork.peek2(syn::token::Impl) {
+			Ok(Self::Impl(input.parse()?))
+		} else if fork.peek(syn::token::Trait) || fork.peek(syn::token::Unsafe) && fork.peek2(syn::token::Trait) {
+			Ok(Self::Trait(input.parse()?))
 		} else {
-			Err(syn::Error::new(proc_macro2::Span::call_site(), "#[inline_sql]: attribute must be placed on a function"))
+			Err(syn::Error::new(proc_macro2::Span::call_site(), "#[inline_sql]: attribute must be placed on a function, an `impl` block or a trait"))
 		}
 	}
 }
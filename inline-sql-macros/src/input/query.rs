@@ -1,33 +1,86 @@
-use proc_macro2::{TokenStream, TokenTree, Delimiter, Group, Span, Ident};
+use proc_macro2::{TokenStream, TokenTree, Delimiter, Group, Spacing, Span};
+use quote::ToTokens;
 
 type TokenTreeIterator = std::iter::Peekable<<TokenStream as IntoIterator>::IntoIter>;
 
 pub struct QueryMacro {
+	#[allow(unused)]
 	pub keyword: keywords::query,
+	#[allow(unused)]
 	pub exclamation: syn::token::Not,
 	pub delimiter: syn::MacroDelimiter,
-	pub query: Query,
+	pub tokens: TokenStream,
+}
+
+impl QueryMacro {
+	// The span of the delimited query, including the delimiters.
+	pub fn span(&self) -> Span {
+		match &self.delimiter {
+			syn::MacroDelimiter::Paren(x) => x.span.join(),
+			syn::MacroDelimiter::Brace(x) => x.span.join(),
+			syn::MacroDelimiter::Bracket(x) => x.span.join(),
+		}
+	}
 }
 
 pub struct Query {
 	pub query: String,
-	pub placeholders: Vec<Ident>,
+	pub placeholders: Vec<syn::Expr>,
+}
+
+#[derive(Copy, Clone)]
+pub enum PlaceholderStyle {
+	Dollar,
+	QuestionMark,
 }
 
 impl Query {
-	pub fn from_tokens(tokens: TokenStream) -> Result<Self, syn::Error> {
-		use std::fmt::Write;
-		let mut parser = QueryParser::new(tokens);
-		let mut query = String::new();
+	// Convert the tokens of a query to SQL.
+	//
+	// The source text of the `source` span is used to copy the indentation of each line, if the compiler provides it.
+	pub fn from_tokens(tokens: TokenStream, source: Span, style: PlaceholderStyle, sigil: char) -> Result<Self, syn::Error> {
+		// A single string literal is used as raw SQL, so it can contain anything the Rust lexer would reject.
+		if let Ok(literal) = syn::parse2::<syn::LitStr>(tokens.clone()) {
+			return Self::from_sql(&literal.value(), literal.span(), style, sigil);
+		}
+
+		let mut parser = QueryParser::new(tokens, sigil);
+		let mut events = Vec::new();
 		while let Some(event) = parser.next()? {
-			if !query.is_empty() {
-				query.push(' ')
+			events.push(event);
+		}
+
+		// Reconstruct the whitespace between tokens from their line and column numbers if possible.
+		// Indentation is made relative to the least indented line of the query.
+		let indent = events.iter()
+			.zip(events.iter().skip(1))
+			.filter(|((prev, _, _), (next, _, _))| !matches!(prev, Event::Separator) && !matches!(next, Event::Separator))
+			.filter(|((_, _, prev_end), (_, start, _))| start.start().line > prev_end.end().line)
+			.map(|(_, (_, start, _))| start.start().column)
+			.min()
+			.unwrap_or(0);
+
+		let source = Source::new(source);
+		let mut query = String::new();
+		let mut prev_end: Option<Span> = None;
+		for (event, start, end) in events {
+			if let Event::Separator = event {
+				if !query.is_empty() && !query.ends_with(' ') {
+					query.push(' ');
+				}
+				prev_end = None;
+				continue;
 			}
+			if let Some(prev_end) = prev_end {
+				push_whitespace(&mut query, &source, prev_end, start, indent);
+			}
+			prev_end = Some(end);
 			match event {
-				Event::GroupOpen(group) => query.push(open_char(&group)?),
-				Event::GroupClose(group) => query.push(close_char(&group)?),
-				Event::Placeholder(pos) => write!(query, "${pos}").unwrap(),
+				Event::GroupOpen(group) => query.push(open_char(&group)),
+				Event::GroupClose(group) => query.push(close_char(&group)),
+				Event::Placeholder(pos) => style.push_placeholder(&mut query, pos),
 				Event::Literal(lit) => query.push_str(&lit),
+				Event::Separator => unreachable!(),
 			}
 		}
 		Ok(Self {
@@ -35,6 +88,347 @@ impl Query {
 			placeholders: parser.placeholders,
 		})
 	}
+
+	// Parse a raw SQL string, replacing `$name` placeholders and leaving everything else untouched.
+	//
+	// String literals, quoted identifiers, comments and dollar-quoted strings are skipped.
+	// The span is used for errors and for the tokens of the placeholders.
+	pub fn from_sql(sql: &str, span: Span, style: PlaceholderStyle, sigil: char) -> Result<Self, syn::Error> {
+		let error = |message: &str| syn::Error::new(span, format!("#[inline_sql]: {message}"));
+		// The sigil is always an ASCII character, so we can compare it with single bytes.
+		let sigil = sigil as u8;
+		let bytes = sql.as_bytes();
+		let mut query = String::with_capacity(sql.len());
+		let mut placeholders = Vec::new();
+		let mut copied = 0;
+		let mut i = 0;
+		while i < bytes.len() {
+			match bytes[i] {
+				b'\'' => {
+					// Strings like E'\'' use backslash escapes.
+					let escape = i >= 1
+						&& bytes[i - 1].eq_ignore_ascii_case(&b'e')
+						&& (i < 2 || !is_ident_byte(bytes[i - 2]));
+					i = skip_quoted(bytes, i, escape)
+						.ok_or_else(|| error("unterminated string literal in query"))?;
+				},
+				b'"' => {
+					i = skip_quoted(bytes, i, false)
+						.ok_or_else(|| error("unterminated quoted identifier in query"))?;
+				},
+				b'-' if bytes.get(i + 1) == Some(&b'-') => {
+					i = sql[i..].find('\n').map(|end| i + end + 1).unwrap_or(bytes.len());
+				},
+				b'/' if bytes.get(i + 1) == Some(&b'*') => {
+					i = skip_block_comment(bytes, i)
+						.ok_or_else(|| error("unterminated block comment in query"))?;
+				},
+				b':' if bytes.get(i + 1) == Some(&b':') => {
+					// A type cast, not a placeholder.
+					i += 2;
+				},
+				b'$' if i >= 1 && is_ident_byte(bytes[i - 1]) => {
+					// A `$` inside an identifier.
+					i += 1;
+				},
+				byte if byte == sigil && matches!(bytes.get(i + 1), Some(b'(' | b'{')) => {
+					// A placeholder expression like `$(page * size)` or `${name}`.
+					let end = find_closing(bytes, i + 1)
+						.ok_or_else(|| error("unterminated placeholder expression in query"))?;
+					let expr = parse_placeholder_expr(&sql[i + 2..end], span)?;
+					query.push_str(&sql[copied..i]);
+					style.push_placeholder(&mut query, map_placeholder(&mut placeholders, expr));
+					copied = end + 1;
+					i = end + 1;
+				},
+				b'$' => {
+					let name_end = (i + 1..bytes.len())
+						.find(|&j| !is_ident_byte(bytes[j]))
+						.unwrap_or(bytes.len());
+					let name = &sql[i + 1..name_end];
+					if name.starts_with(|c: char| c.is_ascii_digit()) {
+						return Err(error(&format!("positional parameter `${name}` is not supported, use `$name` to refer to a function argument")));
+					} else if bytes.get(name_end) == Some(&b'$') {
+						// A dollar-quoted string like `$$...$$` or `$body$...$body$`.
+						let tag = &sql[i..=name_end];
+						let end = sql[name_end + 1..].find(tag)
+							.ok_or_else(|| error(&format!("unterminated dollar-quoted string `{tag}` in query")))?;
+						i = name_end + 1 + end + tag.len();
+					} else if sigil != b'$' {
+						i += 1;
+					} else if name.is_empty() {
+						return Err(error("expected placeholder name after `$`"));
+					} else {
+						let end = placeholder_name_end(bytes, name_end);
+						let expr = parse_placeholder_expr(&sql[i + 1..end], span)?;
+						query.push_str(&sql[copied..i]);
+						style.push_placeholder(&mut query, map_placeholder(&mut placeholders, expr));
+						copied = end;
+						i = end;
+					}
+				},
+				byte if byte == sigil && bytes.get(i + 1).is_some_and(|&x| is_ident_byte(x) && !x.is_ascii_digit()) => {
+					let name_end = (i + 1..bytes.len())
+						.find(|&j| !is_ident_byte(bytes[j]))
+						.unwrap_or(bytes.len());
+					let end = placeholder_name_end(bytes, name_end);
+					let expr = parse_placeholder_expr(&sql[i + 1..end], span)?;
+					query.push_str(&sql[copied..i]);
+					style.push_placeholder(&mut query, map_placeholder(&mut placeholders, expr));
+					copied = end;
+					i = end;
+				},
+				byte if is_ident_byte(byte) => {
+					i = (i..bytes.len())
+						.find(|&j| !is_ident_byte(bytes[j]))
+						.unwrap_or(bytes.len());
+				},
+				_ => i += 1,
+			}
+		}
+		query.push_str(&sql[copied..]);
+		Ok(Self {
+			query,
+			placeholders,
+		})
+	}
+}
+
+fn has_location(span: Span) -> bool {
+	// Lines start at 1, so line 0 means the compiler did not give us location information.
+	// Tokens are never empty, so an empty span (like `Span::call_site()` outside of a proc macro) has no location either.
+	let start = span.start();
+	start.line != 0 && start != span.end()
+}
+
+// The source text of a query, with the location where it starts.
+struct Source {
+	text: Option<String>,
+	start: proc_macro2::LineColumn,
+}
+
+impl Source {
+	fn new(span: Span) -> Self {
+		Self {
+			text: span.source_text().filter(|_| has_location(span)),
+			start: span.start(),
+		}
+	}
+
+	// Get the indentation of the line of a token, without the first `indent` characters.
+	//
+	// Returns `None` if the token is not part of the source text, like tokens from a `macro_rules!` fragment.
+	fn indentation(&self, token: Span, indent: usize) -> Option<&str> {
+		let start = token.start();
+		let line = self.text.as_deref()?
+			.split('\n')
+			.nth(start.line.checked_sub(self.start.line).filter(|&line| line > 0)?)?;
+		let (prefix_len, _) = line.char_indices().nth(start.column)?;
+		let (prefix, rest) = line.split_at(prefix_len);
+		if !prefix.chars().all(char::is_whitespace) || !rest.starts_with(&token.source_text()?) {
+			return None;
+		}
+		let (indent_len, _) = prefix.char_indices().nth(indent).unwrap_or((prefix.len(), ' '));
+		Some(&prefix[indent_len..])
+	}
+}
+
+fn push_whitespace(query: &mut String, source: &Source, prev_end: Span, start: Span, indent: usize) {
+	if !has_location(prev_end) || !has_location(start) {
+		query.push(' ');
+		return;
+	}
+	let token = start;
+	let prev_end = prev_end.end();
+	let start = start.start();
+	if start.line > prev_end.line {
+		query.push_str(&"\n".repeat(start.line - prev_end.line));
+		match source.indentation(token, indent) {
+			Some(indentation) => query.push_str(indentation),
+			None => query.push_str(&" ".repeat(start.column.saturating_sub(indent))),
+		}
+	} else if start.line == prev_end.line && start.column >= prev_end.column {
+		query.push_str(&" ".repeat(start.column - prev_end.column));
+	} else {
+		query.push(' ');
+	}
+}
+
+impl PlaceholderStyle {
+	fn push_placeholder(self, query: &mut String, pos: usize) {
+		use std::fmt::Write;
+		match self {
+			Self::Dollar => write!(query, "${pos}").unwrap(),
+			Self::QuestionMark => write!(query, "?{pos}").unwrap(),
+		}
+	}
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+	// Non-ASCII bytes are treated as part of an identifier, so we never split a UTF-8 sequence.
+	byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+// Skip a quoted string or identifier starting at `start`, returning the index after the closing quote.
+//
+// Doubled quotes are handled as two adjacent strings, which gives the same result.
+fn skip_quoted(bytes: &[u8], start: usize, backslash_escapes: bool) -> Option<usize> {
+	let quote = bytes[start];
+	let mut i = start + 1;
+	while i < bytes.len() {
+		if backslash_escapes && bytes[i] == b'\\' {
+			i += 2;
+		} else if bytes[i] == quote {
+			return Some(i + 1);
+		} else {
+			i += 1;
+		}
+	}
+	None
+}
+
+// Skip a (possibly nested) block comment starting at `start`, returning the index after the comment.
+fn skip_block_comment(bytes: &[u8], start: usize) -> Option<usize> {
+	let mut depth = 0;
+	let mut i = start;
+	while i + 1 < bytes.len() {
+		if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+			depth += 1;
+			i += 2;
+		} else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+			depth -= 1;
+			i += 2;
+			if depth == 0 {
+				return Some(i);
+			}
+		} else {
+			i += 1;
+		}
+	}
+	None
+}
+
+// Extend a placeholder name that ends at `name_end` with field accesses like `$filter.species`.
+fn placeholder_name_end(bytes: &[u8], name_end: usize) -> usize {
+	let mut end = name_end;
+	while bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(|&x| is_ident_byte(x)) {
+		end = (end + 1..bytes.len())
+			.find(|&j| !is_ident_byte(bytes[j]))
+			.unwrap_or(bytes.len());
+	}
+	end
+}
+
+// Find the bracket that closes the one at `start`, returning its index.
+//
+// Brackets inside Rust string and character literals are skipped, like in `$(format!(")"))`.
+fn find_closing(bytes: &[u8], start: usize) -> Option<usize> {
+	let open = bytes[start];
+	let close = match open {
+		b'(' => b')',
+		b'{' => b'}',
+		_ => return None,
+	};
+	let mut depth = 0;
+	let mut i = start;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'"' => i = skip_quoted(bytes, i, true)?,
+			b'\'' => i = skip_char_literal(bytes, i)?,
+			byte if is_ident_byte(byte) => {
+				let end = (i..bytes.len())
+					.find(|&j| !is_ident_byte(bytes[j]))
+					.unwrap_or(bytes.len());
+				i = match &bytes[i..end] {
+					b"r" | b"br" | b"cr" => skip_raw_string(bytes, end).unwrap_or(end),
+					_ => end,
+				};
+			},
+			byte if byte == open => {
+				depth += 1;
+				i += 1;
+			},
+			byte if byte == close => {
+				depth -= 1;
+				if depth == 0 {
+					return Some(i);
+				}
+				i += 1;
+			},
+			_ => i += 1,
+		}
+	}
+	None
+}
+
+// Skip a Rust character literal starting at `start`, returning the index after the closing quote.
+//
+// Lifetimes and labels like `'a` have no closing quote, so only the quote itself is skipped.
+fn skip_char_literal(bytes: &[u8], start: usize) -> Option<usize> {
+	let first = match bytes.get(start + 1) {
+		Some(b'\\') => return skip_quoted(bytes, start, true),
+		Some(&first) => first,
+		None => return Some(start + 1),
+	};
+	let len = match first {
+		0x00..=0x7F => 1,
+		0x80..=0xDF => 2,
+		0xE0..=0xEF => 3,
+		_ => 4,
+	};
+	if bytes.get(start + 1 + len) == Some(&b'\'') {
+		Some(start + 2 + len)
+	} else {
+		Some(start + 1)
+	}
+}
+
+// Skip a raw string like `r#"..."#` after its prefix, returning the index after the closing quote and hashes.
+fn skip_raw_string(bytes: &[u8], start: usize) -> Option<usize> {
+	let hashes = bytes[start..].iter().take_while(|&&byte| byte == b'#').count();
+	if bytes.get(start + hashes) != Some(&b'"') {
+		return None;
+	}
+	let mut i = start + hashes + 1;
+	while i < bytes.len() {
+		if bytes[i] == b'"' && bytes[i + 1..].iter().take(hashes).filter(|&&byte| byte == b'#').count() == hashes {
+			return Some(i + 1 + hashes);
+		}
+		i += 1;
+	}
+	None
+}
+
+fn parse_placeholder_expr(text: &str, span: Span) -> Result<syn::Expr, syn::Error> {
+	let error = || syn::Error::new(span, format!("#[inline_sql]: invalid placeholder expression `{text}`"));
+	let tokens: TokenStream = text.parse().map_err(|_| error())?;
+	syn::parse2(respan(tokens, span)).map_err(|_| error())
+}
+
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+	tokens.into_iter()
+		.map(|mut tree| {
+			if let TokenTree::Group(group) = &tree {
+				let mut new_group = Group::new(group.delimiter(), respan(group.stream(), span));
+				new_group.set_span(span);
+				tree = TokenTree::Group(new_group);
+			} else {
+				tree.set_span(span);
+			}
+			tree
+		})
+		.collect()
+}
+
+// Identical placeholder expressions are sent to the database only once.
+fn map_placeholder(placeholders: &mut Vec<syn::Expr>, expr: syn::Expr) -> usize {
+	let tokens = expr.to_token_stream().to_string();
+	if let Some(pos) = placeholders.iter().position(|x| x.to_token_stream().to_string() == tokens) {
+		pos + 1
+	} else {
+		placeholders.push(expr);
+		placeholders.len()
+	}
 }
 
 mod keywords {
@@ -46,12 +440,11 @@ impl syn::parse::Parse for QueryMacro {
 		let keyword: keywords::query = input.parse()?;
 		let exclamation: syn::token::Not = input.parse()?;
 		let (delimiter, tokens) = parse_delimiter(input)?;
-		let query = Query::from_tokens(tokens)?;
 		Ok(Self {
 			keyword,
 			exclamation,
 			delimiter,
-			query,
+			tokens,
 		})
 	}
 }
@@ -77,18 +470,21 @@ fn parse_delimiter(input: syn::parse::ParseStream) -> Result<(syn::MacroDelimite
 
 struct QueryParser {
 	stack: Vec<(TokenTreeIterator, Option<Group>)>,
-	placeholders: Vec<Ident>,
+	placeholders: Vec<syn::Expr>,
+	sigil: char,
 }
 
 impl QueryParser {
-	fn new(tokens: TokenStream) -> Self {
+	fn new(tokens: TokenStream, sigil: char) -> Self {
 		Self {
 			stack: vec![(tokens.into_iter().peekable(), None)],
 			placeholders: Vec::new(),
+			sigil,
 		}
 	}
 
-	fn next(&mut self) -> Result<Option<Event>, syn::Error> {
+	// Get the next event with the span of its first and last token.
+	fn next(&mut self) -> Result<Option<(Event, Span, Span)>, syn::Error> {
 		let (tokens, _group) = match self.stack.last_mut() {
 			Some(x) => x,
 			None => return Ok(None),
@@ -99,8 +495,15 @@ impl QueryParser {
 			None => {
 				let (_, group) = self.stack.pop().unwrap();
 				match group {
+					// Groups without delimiters come from `macro_rules!` fragments, so they are flattened.
+					// Their tokens can come from a different place in the source, so they are separated by a single space.
+					Some(group) if group.delimiter() == Delimiter::None => {
+						let span = group.span_close();
+						return Ok(Some((Event::Separator, span, span)));
+					},
 					Some(group) => {
-						return Ok(Some(Event::GroupClose(group)));
+						let span = group.span_close();
+						return Ok(Some((Event::GroupClose(group), span, span)));
 					},
 					None => {
 						if self.stack.is_empty() {
@@ -114,55 +517,98 @@ impl QueryParser {
 		};
 
 		match tree {
+			TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+				let span = group.span_open();
+				self.stack.push((group.stream().into_iter().peekable(), Some(group)));
+				Ok(Some((Event::Separator, span, span)))
+			},
 			TokenTree::Group(group) => {
 				self.stack.push((group.stream().into_iter().peekable(), Some(group.clone())));
-				Ok(Some(Event::GroupOpen(group)))
+				let span = group.span_open();
+				Ok(Some((Event::GroupOpen(group), span, span)))
 			},
 			TokenTree::Ident(ident) => {
-				Ok(Some(Event::Literal(ident.to_string())))
+				Ok(Some((Event::Literal(ident.to_string()), ident.span(), ident.span())))
 			},
 			TokenTree::Punct(punct) => {
-				if punct.as_char() == '$' {
-					let ident = take_placeholder(tokens)
-						.map_err(|span| syn::Error::new(span.unwrap_or(punct.span()), "#[inline_sql]: expected placeholder name"))?;
-					let pos = self.map_placeholder(ident);
-					Ok(Some(Event::Placeholder(pos)))
+				let is_cast = punct.as_char() == ':'
+					&& punct.spacing() == Spacing::Joint
+					&& matches!(tokens.peek(), Some(TokenTree::Punct(next)) if next.as_char() == ':');
+				let is_placeholder = punct.as_char() == self.sigil
+					&& !is_cast
+					&& (self.sigil == '$' || starts_placeholder(tokens.peek()));
+				if is_placeholder {
+					let (expr, end) = take_placeholder(tokens, punct.span())?;
+					let pos = map_placeholder(&mut self.placeholders, expr);
+					Ok(Some((Event::Placeholder(pos), punct.span(), end)))
+				} else if is_cast {
+					let end = tokens.next().unwrap().span();
+					Ok(Some((Event::Literal("::".into()), punct.span(), end)))
 				} else {
+					// Without location information, consecutive punctuation is joined without spaces.
 					let mut data = punct.to_string();
+					let mut end = punct.span();
 					while let Some(TokenTree::Punct(punct)) = tokens.peek() {
-						if punct.as_char() == '$' {
+						if punct.as_char() == self.sigil || has_location(punct.span()) {
 							break;
 						}
 						data.push(punct.as_char());
+						end = punct.span();
 						tokens.next();
 					}
-					Ok(Some(Event::Literal(data)))
+					Ok(Some((Event::Literal(data), punct.span(), end)))
 				}
 			},
 			TokenTree::Literal(literal) => {
-				Ok(Some(Event::Literal(literal.to_string())))
+				Ok(Some((Event::Literal(literal.to_string()), literal.span(), literal.span())))
 			}
 		}
 	}
+}
 
-	#[allow(clippy::cmp_owned)]
-	fn map_placeholder(&mut self, ident: Ident) -> usize {
-		let name = ident.to_string();
-		if let Some(pos) = self.placeholders.iter().position(|x| x.to_string() == name) {
-			pos + 1
-		} else {
-			let pos = self.placeholders.len();
-			self.placeholders.push(ident);
-			pos + 1
-		}
+// Take a placeholder after the sigil, returning the expression and the span of its last token.
+//
+// A placeholder is a name with optional field accesses (`$filter.species`),
+// or an expression in parentheses or braces (`$(page * size)` or `${name}`).
+fn take_placeholder(tokens: &mut TokenTreeIterator, sigil_span: Span) -> Result<(syn::Expr, Span), syn::Error> {
+	let expected_placeholder = |span| syn::Error::new(span, "#[inline_sql]: expected placeholder name or expression");
+	match tokens.next() {
+		Some(TokenTree::Group(group)) if matches!(group.delimiter(), Delimiter::Parenthesis | Delimiter::Brace | Delimiter::None) => {
+			let expr = syn::parse2(group.stream())?;
+			Ok((expr, group.span_close()))
+		},
+		Some(TokenTree::Ident(ident)) => {
+			let mut end = ident.span();
+			let mut expr_tokens = TokenStream::from(TokenTree::Ident(ident));
+			while let Some(TokenTree::Punct(dot)) = tokens.peek() {
+				if dot.as_char() != '.' {
+					break;
+				}
+				expr_tokens.extend(tokens.next());
+				match tokens.next() {
+					Some(TokenTree::Ident(field)) => {
+						end = field.span();
+						expr_tokens.extend([TokenTree::Ident(field)]);
+					},
+					Some(TokenTree::Literal(index)) => {
+						end = index.span();
+						expr_tokens.extend([TokenTree::Literal(index)]);
+					},
+					other => return Err(syn::Error::new(other.map(|x| x.span()).unwrap_or(end), "#[inline_sql]: expected field name after `.`")),
+				}
+			}
+			Ok((syn::parse2(expr_tokens)?, end))
+		},
+		None => Err(expected_placeholder(sigil_span)),
+		Some(other) => Err(expected_placeholder(other.span())),
 	}
 }
 
-fn take_placeholder(tokens: &mut TokenTreeIterator) -> Result<Ident, Option<Span>> {
-	match tokens.next() {
-		Some(TokenTree::Ident(ident)) => Ok(ident),
-		None => Err(None),
-		Some(other) => Err(Some(other.span())),
+fn starts_placeholder(token: Option<&TokenTree>) -> bool {
+	match token {
+		Some(TokenTree::Ident(_)) => true,
+		Some(TokenTree::Group(group)) => matches!(group.delimiter(), Delimiter::Parenthesis | Delimiter::Brace | Delimiter::None),
+		_ => false,
 	}
 }
 
@@ -171,23 +617,166 @@ enum Event {
 	GroupClose(Group),
 	Placeholder(usize),
 	Literal(String),
+	Separator,
 }
 
 
-fn open_char(group: &Group) -> Result<char, syn::Error> {
+fn open_char(group: &Group) -> char {
 	match group.delimiter() {
-		Delimiter::None => Err(syn::Error::new(group.span(), "#[inline-sql] encountered a none-delimited group in the query")),
-		Delimiter::Brace => Ok('{'),
-		Delimiter::Parenthesis => Ok('('),
-		Delimiter::Bracket => Ok('['),
+		Delimiter::None => unreachable!("none-delimited groups are flattened"),
+		Delimiter::Brace => '{',
+		Delimiter::Parenthesis => '(',
+		Delimiter::Bracket => '[',
 	}
 }
 
-fn close_char(group: &Group) -> Result<char, syn::Error> {
+fn close_char(group: &Group) -> char {
 	match group.delimiter() {
-		Delimiter::None => Err(syn::Error::new(group.span(), "#[inline-sql] encountered a none-delimited group in the query")),
-		Delimiter::Brace => Ok('}'),
-		Delimiter::Parenthesis => Ok(')'),
-		Delimiter::Bracket => Ok(']'),
+		Delimiter::None => unreachable!("none-delimited groups are flattened"),
+		Delimiter::Brace => '}',
+		Delimiter::Parenthesis => ')',
+		Delimiter::Bracket => ']',
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn from_sql(sql: &str, style: PlaceholderStyle, sigil: char) -> Result<(String, Vec<String>), syn::Error> {
+		let query = Query::from_sql(sql, Span::call_site(), style, sigil)?;
+		let placeholders = query.placeholders.iter()
+			.map(|expr| expr.to_token_stream().to_string())
+			.collect();
+		Ok((query.query, placeholders))
+	}
+
+	fn dollar(sql: &str) -> (String, Vec<String>) {
+		from_sql(sql, PlaceholderStyle::Dollar, '$').unwrap()
+	}
+
+	#[test]
+	fn string_literals_are_skipped() {
+		assert_eq!(dollar("SELECT '$a', 'it''s $b' WHERE x = $c"), ("SELECT '$a', 'it''s $b' WHERE x = $1".into(), vec!["c".into()]));
+	}
+
+	#[test]
+	fn escape_strings_are_skipped() {
+		assert_eq!(dollar(r"SELECT E'it\'s $a', e'\\' WHERE x = $b"), (r"SELECT E'it\'s $a', e'\\' WHERE x = $1".into(), vec!["b".into()]));
+	}
+
+	#[test]
+	fn quoted_identifiers_are_skipped() {
+		assert_eq!(dollar(r#"SELECT "$a" FROM "pets" WHERE x = $b"#), (r#"SELECT "$a" FROM "pets" WHERE x = $1"#.into(), vec!["b".into()]));
+	}
+
+	#[test]
+	fn line_comments_are_skipped() {
+		assert_eq!(dollar("SELECT 1 -- $a\nWHERE x = $b"), ("SELECT 1 -- $a\nWHERE x = $1".into(), vec!["b".into()]));
+	}
+
+	#[test]
+	fn nested_block_comments_are_skipped() {
+		assert_eq!(dollar("SELECT /* $a /* $b */ $c */ $d"), ("SELECT /* $a /* $b */ $c */ $1".into(), vec!["d".into()]));
+	}
+
+	#[test]
+	fn dollar_quoted_strings_are_skipped() {
+		assert_eq!(dollar("SELECT $$ $a $$, $body$ $b $$ $body$, $c"), ("SELECT $$ $a $$, $body$ $b $$ $body$, $1".into(), vec!["c".into()]));
+	}
+
+	#[test]
+	fn dollar_inside_identifier_is_not_a_placeholder() {
+		assert_eq!(dollar("SELECT a$b FROM t WHERE x = $c"), ("SELECT a$b FROM t WHERE x = $1".into(), vec!["c".into()]));
+	}
+
+	#[test]
+	fn positional_parameters_are_rejected() {
+		let e = from_sql("SELECT * FROM t WHERE x = $1", PlaceholderStyle::Dollar, '$').unwrap_err();
+		assert!(e.to_string().contains("positional parameter `$1` is not supported"));
+	}
+
+	#[test]
+	fn alternate_sigil() {
+		let query = from_sql("SELECT $$:a$$, x::int FROM t WHERE y = :b.c AND z = :b.c", PlaceholderStyle::QuestionMark, ':').unwrap();
+		assert_eq!(query, ("SELECT $$:a$$, x::int FROM t WHERE y = ?1 AND z = ?1".into(), vec!["b . c".into()]));
+	}
+
+	#[test]
+	fn placeholder_expressions_skip_rust_literals() {
+		assert_eq!(dollar(r#"SELECT $(format!(")")), $(f(')', '\'', r"(")) AS x"#), (
+			"SELECT $1, $2 AS x".into(),
+			vec![r#"format ! (")")"#.into(), r#"f (')' , '\'' , r"(")"#.into()],
+		));
+	}
+
+	fn from_tokens(tokens: TokenStream) -> String {
+		Query::from_tokens(tokens, Span::call_site(), PlaceholderStyle::Dollar, '$').unwrap().query
+	}
+
+	// Parse a delimited query, to use the source text of the group for the indentation.
+	fn from_group(source: &str) -> String {
+		let group = match source.parse::<TokenStream>().unwrap().into_iter().next() {
+			Some(TokenTree::Group(group)) => group,
+			_ => panic!("expected a group"),
+		};
+		Query::from_tokens(group.stream(), group.span(), PlaceholderStyle::Dollar, '$').unwrap().query
+	}
+
+	#[test]
+	fn indentation_is_relative_to_least_indented_line() {
+		let tokens = "\n    SELECT name\n    FROM pets\n      WHERE species = $species\n".parse().unwrap();
+		assert_eq!(from_tokens(tokens), "SELECT name\nFROM pets\n  WHERE species = $1");
+	}
+
+	#[test]
+	fn tabs_are_copied_from_the_source() {
+		let query = from_group("(\n\t\tSELECT name\n\t\tFROM pets\n\t\t\tWHERE species = $species\n\t\t\t  AND age > 3\n\t)");
+		assert_eq!(query, "SELECT name\nFROM pets\n\tWHERE species = $1\n\t  AND age > 3");
+	}
+
+	#[test]
+	fn tabs_count_as_one_column_without_source_text() {
+		let tokens = "\n\t\tSELECT name\n\t\tFROM pets\n\t\t\tWHERE species = $species".parse().unwrap();
+		assert_eq!(from_tokens(tokens), "SELECT name\nFROM pets\n WHERE species = $1");
+	}
+
+	#[test]
+	fn tokens_from_elsewhere_count_columns() {
+		// The source text of the group does not match the tokens, like for a `macro_rules!` fragment.
+		let group = match "(\nSELECT x\n\tJOIN t\n\t\tAND y)".parse::<TokenStream>().unwrap().into_iter().next() {
+			Some(TokenTree::Group(group)) => group,
+			_ => panic!("expected a group"),
+		};
+		let tokens: TokenStream = "\nSELECT x\n\tFROM t\n\t\tWHERE y".parse().unwrap();
+		let query = Query::from_tokens(tokens, group.span(), PlaceholderStyle::Dollar, '$').unwrap().query;
+		assert_eq!(query, "SELECT x\nFROM t\n WHERE y");
+	}
+
+	#[test]
+	fn casts_keep_their_spacing() {
+		let tokens = "SELECT x::int, y :: text, 'a'::text FROM t".parse().unwrap();
+		assert_eq!(from_tokens(tokens), "SELECT x::int, y :: text, 'a'::text FROM t");
+	}
+
+	#[test]
+	fn comparison_operators_are_not_split() {
+		let tokens = "WHERE a >= 1 AND b <> 2 AND c<=$c".parse().unwrap();
+		assert_eq!(from_tokens(tokens), "WHERE a >= 1 AND b <> 2 AND c<=$1");
+	}
+
+	#[test]
+	fn tokens_without_location_are_separated_by_spaces() {
+		let tokens = quote::quote!(SELECT x::int FROM t WHERE a >= 1 AND b <> $c);
+		assert_eq!(from_tokens(tokens), "SELECT x :: int FROM t WHERE a >= 1 AND b <> $1");
+	}
+
+	#[test]
+	fn groups_without_delimiters_are_separated_by_spaces() {
+		// Fragments from `macro_rules!` are wrapped in a group without delimiters, with tokens from elsewhere in the source.
+		let mut tokens: TokenStream = "SELECT * FROM t WHERE".parse().unwrap();
+		tokens.extend([TokenTree::Group(Group::new(Delimiter::None, "species = $species".parse().unwrap()))]);
+		tokens.extend("AND x <> 1".parse::<TokenStream>().unwrap());
+		assert_eq!(from_tokens(tokens), "SELECT * FROM t WHERE species = $1 AND x <> 1");
 	}
 }
@@ -0,0 +1,133 @@
+pub struct SqlFile {
+	pub path: String,
+	pub contents: String,
+}
+
+impl SqlFile {
+	// Read a file relative to the manifest directory of the crate being compiled.
+	pub fn read(path: &syn::LitStr) -> Result<Self, syn::Error> {
+		let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+			.ok_or_else(|| syn::Error::new(path.span(), "#[inline_sql]: CARGO_MANIFEST_DIR is not set, can not resolve the `file` argument"))?;
+		let full_path = std::path::Path::new(&manifest_dir).join(path.value());
+		let contents = std::fs::read_to_string(&full_path)
+			.map_err(|e| syn::Error::new(path.span(), format!("#[inline_sql]: failed to read {}: {e}", full_path.display())))?;
+		let full_path = full_path.to_str()
+			.ok_or_else(|| syn::Error::new(path.span(), format!("#[inline_sql]: path is not valid UTF-8: {}", full_path.display())))?;
+		Ok(Self {
+			path: full_path.into(),
+			contents,
+		})
+	}
+
+	// Select a named query from a file with `-- name: ...` headers.
+	// The query runs from the line after the header until the next header or the end of the file.
+	pub fn select(&self, name: &syn::LitStr) -> Result<&str, syn::Error> {
+		let queries = self.named_queries();
+		for (i, first) in queries.iter().enumerate() {
+			if let Some(second) = queries[i + 1..].iter().find(|query| query.name == first.name) {
+				return Err(syn::Error::new(name.span(), format!(
+					"#[inline_sql]: query `{}` is defined more than once in {}, on line {} and line {}",
+					first.name, self.path, first.line, second.line,
+				)));
+			}
+		}
+		if let Some(query) = queries.iter().find(|query| query.name == name.value()) {
+			return Ok(query.body);
+		}
+
+		let available: Vec<_> = queries.iter().map(|query| format!("`{}`", query.name)).collect();
+		let note = if available.is_empty() {
+			"Note: the file does not contain any `-- name: ...` headers.".to_string()
+		} else {
+			format!("Note: available names are: {}", available.join(", "))
+		};
+		Err(syn::Error::new(name.span(), format!("#[inline_sql]: query `{}` not found in {}\n\n{note}", name.value(), self.path)))
+	}
+
+	fn named_queries(&self) -> Vec<NamedQuery<'_>> {
+		let mut queries = Vec::new();
+		let mut current: Option<(&str, usize, usize)> = None;
+		let mut offset = 0;
+		for (index, line) in self.contents.split_inclusive('\n').enumerate() {
+			if let Some(name) = parse_name_header(line) {
+				if let Some((name, line, start)) = current {
+					queries.push(NamedQuery { name, line, body: self.contents[start..offset].trim() });
+				}
+				current = Some((name, index + 1, offset + line.len()));
+			}
+			offset += line.len();
+		}
+		if let Some((name, line, start)) = current {
+			queries.push(NamedQuery { name, line, body: self.contents[start..].trim() });
+		}
+		queries
+	}
+}
+
+// A query from a file with `-- name: ...` headers.
+#[derive(Debug, PartialEq)]
+struct NamedQuery<'a> {
+	name: &'a str,
+
+	// The line number of the header, starting at 1.
+	line: usize,
+
+	body: &'a str,
+}
+
+fn parse_name_header(line: &str) -> Option<&str> {
+	let comment = line.trim().strip_prefix("--")?;
+	let name = comment.trim_start().strip_prefix("name:")?;
+	Some(name.trim())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sql_file(contents: &str) -> SqlFile {
+		SqlFile {
+			path: "queries.sql".into(),
+			contents: contents.into(),
+		}
+	}
+
+	fn select(file: &SqlFile, name: &str) -> Result<String, String> {
+		file.select(&syn::LitStr::new(name, proc_macro2::Span::call_site()))
+			.map(|query| query.to_string())
+			.map_err(|e| e.to_string())
+	}
+
+	#[test]
+	fn headers_are_parsed() {
+		let file = sql_file("-- Pets.\n-- name: get_pets\nSELECT * FROM pets;\n--name:count_pets  \nSELECT count(*) FROM pets;\n");
+		assert_eq!(file.named_queries(), [
+			NamedQuery { name: "get_pets", line: 2, body: "SELECT * FROM pets;" },
+			NamedQuery { name: "count_pets", line: 4, body: "SELECT count(*) FROM pets;" },
+		]);
+	}
+
+	#[test]
+	fn bodies_are_trimmed() {
+		let file = sql_file("-- name: a\n\n  SELECT 1\n  FROM t\n\n-- name: b\r\n\tSELECT 2\r\n");
+		assert_eq!(select(&file, "a").unwrap(), "SELECT 1\n  FROM t");
+		assert_eq!(select(&file, "b").unwrap(), "SELECT 2");
+	}
+
+	#[test]
+	fn missing_name() {
+		let file = sql_file("-- name: a\nSELECT 1;\n-- name: b\nSELECT 2;\n");
+		let error = select(&file, "c").unwrap_err();
+		assert_eq!(error, "#[inline_sql]: query `c` not found in queries.sql\n\nNote: available names are: `a`, `b`");
+
+		let error = select(&sql_file("SELECT 1;\n"), "a").unwrap_err();
+		assert!(error.ends_with("Note: the file does not contain any `-- name: ...` headers."));
+	}
+
+	#[test]
+	fn duplicate_name() {
+		let file = sql_file("-- name: a\nSELECT 1;\n-- name: b\nSELECT 2;\n-- name: a\nSELECT 3;\n");
+		let error = select(&file, "b").unwrap_err();
+		assert_eq!(error, "#[inline_sql]: query `a` is defined more than once in queries.sql, on line 1 and line 5");
+	}
+}
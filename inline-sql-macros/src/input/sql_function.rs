@@ -5,6 +5,7 @@ use crate::util;
 pub struct SqlFunction {
 	pub attributes: Vec<syn::Attribute>,
 	pub visibility: syn::Visibility,
+	pub defaultness: Option<syn::Token![default]>,
 	pub signature: syn::Signature,
 	#[allow(unused)]
 	pub brace_token: syn::token::Brace,
@@ -14,13 +15,80 @@ pub struct SqlFunction {
 pub enum QueryType<'a> {
 	Execute,
 	CountRows,
-	List(&'a syn::Type),
-	Optional(&'a syn::Type),
-	One(&'a syn::Type),
+	List(RowType<'a>),
+	Optional(RowType<'a>),
+	One(RowType<'a>),
+	Collect(RowType<'a>),
+	Stream,
+	MappedStream {
+		row: RowType<'a>,
+		error: &'a syn::Type,
+		boxed: bool,
+	},
+	// Consecutive rows with the same parent key are folded into a parent and a `Vec` of children.
+	Grouped {
+		parent: &'a syn::Type,
+		child: &'a syn::Type,
+		key: &'a syn::Expr,
+	},
+}
+
+#[derive(Copy, Clone)]
+pub enum RowType<'a> {
+	// A type that is converted from an entire row.
+	Row(&'a syn::Type),
+
+	// A type that is decoded from the first column of a row.
+	Scalar(&'a syn::Type),
+
+	// A tuple where each element is decoded from the column at the same position.
+	Tuple(&'a syn::Type, &'a syn::punctuated::Punctuated<syn::Type, syn::token::Comma>),
+
+	// A key and value for maps, where the key is decoded from the first column.
+	// If `scalar` is true, the value is decoded from the second column,
+	// otherwise it is converted from the entire row.
+	KeyValue {
+		key: &'a syn::Type,
+		value: &'a syn::Type,
+		scalar: bool,
+	},
+}
+
+#[derive(Copy, Clone)]
+pub enum QueryKind {
+	Execute,
+	CountRows,
+	List,
+	Optional,
+	One,
+	Collect,
 	Stream,
 }
 
 impl SqlFunction {
+	pub fn from_block(attributes: Vec<syn::Attribute>, visibility: syn::Visibility, defaultness: Option<syn::Token![default]>, signature: syn::Signature, block: syn::Block) -> Self {
+		let statements = block.stmts;
+		Self {
+			attributes,
+			visibility,
+			defaultness,
+			signature,
+			brace_token: block.brace_token,
+			body: quote::quote!(#(#statements)*),
+		}
+	}
+
+	// Check if a function body consists of only a `query!` macro.
+	pub fn is_query_block(block: &syn::Block) -> bool {
+		let mac = match block.stmts.as_slice() {
+			[syn::Stmt::Macro(statement)] if statement.semi_token.is_none() => &statement.mac,
+			[syn::Stmt::Expr(syn::Expr::Macro(expr), None)] => &expr.mac,
+			_ => return false,
+		};
+		mac.path.is_ident("query")
+	}
+
+	// Check if the input starts with a function, possibly with attributes and a visibility like `pub fn`.
 	#[allow(clippy::nonminimal_bool)]
 	pub fn peek(input: syn::parse::ParseStream) -> bool {
 		if input.peek(syn::token::Fn) {
@@ -28,6 +96,8 @@ impl SqlFunction {
 		}
 		let fork = input.fork();
 		true
+			&& fork.call(syn::Attribute::parse_outer).is_ok()
+			&& fork.parse::<syn::Visibility>().is_ok()
 			&& fork.parse::<Option<syn::token::Const>>().is_ok()
 			&& fork.parse::<Option<syn::token::Async>>().is_ok()
 			&& fork.parse::<Option<syn::token::Unsafe>>().is_ok()
@@ -42,6 +112,7 @@ impl syn::parse::Parse for SqlFunction {
 		Ok(Self {
 			attributes: syn::Attribute::parse_outer(input)?,
 			visibility: input.parse()?,
+			defaultness: None,
 			signature: input.parse()?,
 			brace_token: syn::braced!(body in input),
 			body: body.parse()?,
@@ -50,12 +121,20 @@ impl syn::parse::Parse for SqlFunction {
 }
 
 impl<'a> QueryType<'a> {
-	pub fn from_return_type(typ: &'a syn::Type) -> Result<Self, syn::Error> {
+	pub fn from_return_type(typ: &'a syn::Type, scalar: bool) -> Result<Self, syn::Error> {
 		let typ = util::type_strip_result(typ)?;
 		if let Some(inner) = util::type_strip_vec(typ) {
-			Ok(Self::List(inner))
+			Ok(Self::List(RowType::new(inner, scalar)))
 		} else if let Some(inner) = util::type_strip_option(typ) {
-			Ok(Self::Optional(inner))
+			Ok(Self::Optional(RowType::new(inner, scalar)))
+		} else if let Some(item) = util::type_strip_collection(typ) {
+			Ok(Self::Collect(RowType::from_collection_item(item, scalar)))
+		} else if let Some(item) = util::type_strip_mapped_stream(typ) {
+			Ok(Self::MappedStream {
+				row: RowType::new(item.row, scalar),
+				error: item.error,
+				boxed: item.boxed,
+			})
 		} else if util::type_is_row_stream(typ) {
 			Ok(Self::Stream)
 		} else if util::type_is_unit(typ) {
@@ -63,13 +142,126 @@ impl<'a> QueryType<'a> {
 		} else if util::type_is_u64(typ) {
 			Ok(Self::CountRows)
 		} else {
-			Err(syn::Error::new_spanned(typ, concat!(
-				"#[inline_sql]: Expected `()`, `u64`, `Vec<_>`, `Option<_>`, `RowStream` or `RowIter`\n\n",
-				"Note: the macro uses the return type to determine what kind and how many results the query returns.\n",
-				"Please make sure the query returns one of the supported types.\n",
-				"If you are using a type alias, the macro can not resolve it.\n",
-				"Replace the alias with the actual type name.",
-			)))
+			Ok(Self::One(RowType::new(typ, scalar)))
+		}
+	}
+
+	pub fn from_kind(kind: QueryKind, row_type: Option<&'a syn::Type>, return_type: &'a syn::Type, scalar: bool) -> Result<Self, syn::Error> {
+		match kind {
+			QueryKind::Execute | QueryKind::CountRows | QueryKind::Stream if row_type.is_some() => {
+				Err(syn::Error::new_spanned(row_type, "#[inline_sql]: the `row` argument can only be used with the `list`, `optional`, `one` and `collect` query kinds"))
+			},
+			QueryKind::Execute => Ok(Self::Execute),
+			QueryKind::CountRows => Ok(Self::CountRows),
+			QueryKind::Stream => Ok(Self::Stream),
+			QueryKind::List => Ok(Self::List(RowType::new(row_type_or_sniff(row_type, return_type, util::type_strip_vec)?, scalar))),
+			QueryKind::Optional => Ok(Self::Optional(RowType::new(row_type_or_sniff(row_type, return_type, util::type_strip_option)?, scalar))),
+			QueryKind::One => Ok(Self::One(RowType::new(row_type_or_sniff(row_type, return_type, Some)?, scalar))),
+			QueryKind::Collect => match row_type {
+				Some(row_type) => Ok(Self::Collect(RowType::new(row_type, scalar))),
+				None => {
+					let item = util::type_result_ok(return_type)
+						.and_then(util::type_strip_collection)
+						.ok_or_else(|| row_type_error(return_type))?;
+					Ok(Self::Collect(RowType::from_collection_item(item, scalar)))
+				},
+			},
+		}
+	}
+
+	// Fold rows into groups for the `group_by` argument.
+	//
+	// The return type must be `Vec<(Parent, Vec<Child>)>`, unless the row type is given as `(Parent, Child)` with the `row` argument.
+	pub fn group_by(self, key: &'a syn::Expr, explicit_row: bool) -> Result<Self, syn::Error> {
+		let (typ, elems) = match self {
+			Self::List(RowType::Tuple(typ, elems)) if elems.len() == 2 => (typ, elems),
+			_ => return Err(syn::Error::new_spanned(key, concat!(
+				"#[inline_sql]: the `group_by` argument requires a return type like `Result<Vec<(Parent, Vec<Child>)>, E>`\n\n",
+				"Note: use `kind = list, row = (Parent, Child)` to return a `Vec` of a type that implements `From<(Parent, Vec<Child>)>`.",
+			))),
+		};
+		let parent = &elems[0];
+		let child = if explicit_row {
+			&elems[1]
+		} else {
+			util::type_strip_vec(&elems[1])
+				.ok_or_else(|| syn::Error::new_spanned(typ, "#[inline_sql]: the `group_by` argument requires the second tuple element to be a `Vec` of children"))?
+		};
+		Ok(Self::Grouped { parent, child, key })
+	}
+}
+
+impl<'a> RowType<'a> {
+	pub fn new(typ: &'a syn::Type, scalar: bool) -> Self {
+		if scalar {
+			Self::Scalar(typ)
+		} else if let Some(elems) = util::type_tuple_elems(typ) {
+			Self::Tuple(typ, elems)
+		} else if util::type_is_scalar(typ) {
+			Self::Scalar(typ)
+		} else {
+			Self::Row(typ)
+		}
+	}
+
+	pub fn from_collection_item(item: util::CollectionItem<'a>, scalar: bool) -> Self {
+		match item {
+			util::CollectionItem::Value(typ) => Self::new(typ, scalar),
+			util::CollectionItem::KeyValue(key, value) => Self::KeyValue {
+				key,
+				value,
+				scalar: scalar || util::type_is_scalar(value),
+			},
+		}
+	}
+}
+
+impl quote::ToTokens for RowType<'_> {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		match self {
+			Self::Row(typ) => typ.to_tokens(tokens),
+			Self::Scalar(typ) => typ.to_tokens(tokens),
+			Self::Tuple(typ, _) => typ.to_tokens(tokens),
+			Self::KeyValue { key, value, .. } => quote::quote!((#key, #value)).to_tokens(tokens),
+		}
+	}
+}
+
+fn row_type_or_sniff<'a>(row_type: Option<&'a syn::Type>, return_type: &'a syn::Type, strip: impl FnOnce(&'a syn::Type) -> Option<&'a syn::Type>) -> Result<&'a syn::Type, syn::Error> {
+	if let Some(row_type) = row_type {
+		return Ok(row_type);
+	}
+	util::type_result_ok(return_type)
+		.and_then(strip)
+		.ok_or_else(|| row_type_error(return_type))
+}
+
+fn row_type_error(return_type: &syn::Type) -> syn::Error {
+	syn::Error::new_spanned(return_type, concat!(
+		"#[inline_sql]: can not determine the row type from the return type\n\n",
+		"Note: add a `row = ...` argument to specify the row type.",
+	))
+}
+
+impl syn::parse::Parse for QueryKind {
+	fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+		let ident: syn::Ident = input.parse()?;
+		if ident == "execute" {
+			Ok(Self::Execute)
+		} else if ident == "count" {
+			Ok(Self::CountRows)
+		} else if ident == "list" {
+			Ok(Self::List)
+		} else if ident == "optional" {
+			Ok(Self::Optional)
+		} else if ident == "one" {
+			Ok(Self::One)
+		} else if ident == "collect" {
+			Ok(Self::Collect)
+		} else if ident == "stream" {
+			Ok(Self::Stream)
+		} else {
+			Err(syn::Error::new_spanned(ident, "#[inline_sql]: unknown query kind, expected one of `execute`, `count`, `list`, `optional`, `one`, `collect` or `stream`"))
 		}
 	}
 }
@@ -18,18 +18,49 @@ pub fn inline_sql(params: proc_macro::TokenStream, item: proc_macro::TokenStream
 }
 
 fn inline_sql_impl(errors: &mut Vec<syn::Error>, params: TokenStream, item: TokenStream) -> TokenStream {
-	let mut args = input::Arguments::new();
-	args.parse_params(errors, params, None);
-
 	let item: input::Item = match syn::parse2(item.clone()) {
 		Ok(x) => x,
 		Err(e) => {
+			// Still report problems in the arguments, before the error for the item itself.
+			input::Arguments::new().parse_params(errors, params, None);
 			errors.push(e);
 			return item;
 		}
 	};
 
+	let mut args = input::Arguments::new();
+	match &item {
+		input::Item::Function(_) => args.parse_params(errors, params, None),
+		input::Item::Impl(_) => args.parse_outer_params(errors, params, "an `impl` block"),
+		input::Item::Trait(_) => args.parse_outer_params(errors, params, "a trait"),
+	}
+
 	match item {
 		input::Item::Function(function) => expand::expand_sql_function(errors, function, args),
+		input::Item::Impl(item) => expand::expand_sql_impl(errors, item, args),
+		input::Item::Trait(item) => expand::expand_sql_trait(errors, item, args),
 	}
 }
+
+#[proc_macro_derive(FromRow, attributes(sql))]
+pub fn derive_from_row(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let mut errors = Vec::new();
+	let output = derive_from_row_impl(&mut errors, item.into());
+
+	let errors = errors.iter().map(|x| x.to_compile_error());
+	quote! {
+		#(#errors)*
+		#output
+	}.into()
+}
+
+fn derive_from_row_impl(errors: &mut Vec<syn::Error>, item: TokenStream) -> TokenStream {
+	let input = match syn::parse2(item).and_then(|input| input::FromRowStruct::from_derive_input(errors, input)) {
+		Ok(x) => x,
+		Err(e) => {
+			errors.push(e);
+			return TokenStream::new();
+		},
+	};
+	expand::expand_from_row(input)
+}
@@ -124,6 +124,107 @@ pub fn type_strip_option(typ: &syn::Type) -> Option<&syn::Type> {
 	}
 }
 
+pub enum CollectionItem<'a> {
+	Value(&'a syn::Type),
+	KeyValue(&'a syn::Type, &'a syn::Type),
+}
+
+pub fn type_strip_collection(typ: &syn::Type) -> Option<CollectionItem<'_>> {
+	let path = type_as_path(typ)?;
+	let last = path.segments.last()?;
+	let arguments = match &last.arguments {
+		syn::PathArguments::AngleBracketed(args) => args,
+		_ => return None,
+	};
+	let mut types = type_args(arguments);
+
+	// Well-known collections, matched only on the last path segment.
+	let lists = [
+		"VecDeque", "LinkedList", "BinaryHeap",
+		"HashSet", "BTreeSet", "IndexSet",
+	];
+	let maps = [
+		"HashMap", "BTreeMap", "IndexMap",
+	];
+	let slices = [
+		"Box", "Rc", "Arc",
+	];
+
+	if lists.iter().any(|name| last.ident == name) {
+		Some(CollectionItem::Value(types.next()?))
+	} else if maps.iter().any(|name| last.ident == name) {
+		Some(CollectionItem::KeyValue(types.next()?, types.next()?))
+	} else if slices.iter().any(|name| last.ident == name) {
+		match type_strip_paren(types.next()?) {
+			syn::Type::Slice(slice) => Some(CollectionItem::Value(&slice.elem)),
+			_ => None,
+		}
+	} else {
+		None
+	}
+}
+
+pub struct MappedStreamItem<'a> {
+	pub row: &'a syn::Type,
+	pub error: &'a syn::Type,
+	pub boxed: bool,
+}
+
+pub fn type_strip_mapped_stream(typ: &syn::Type) -> Option<MappedStreamItem<'_>> {
+	match type_strip_paren(typ) {
+		syn::Type::ImplTrait(impl_trait) => {
+			let item = impl_trait.bounds.iter().find_map(|bound| {
+				let bound = match bound {
+					syn::TypeParamBound::Trait(x) => x,
+					_ => return None,
+				};
+				let last = bound.path.segments.last()?;
+				if last.ident != "Stream" {
+					return None;
+				}
+				let arguments = match &last.arguments {
+					syn::PathArguments::AngleBracketed(args) => args,
+					_ => return None,
+				};
+				arguments.args.iter().find_map(|arg| match arg {
+					syn::GenericArgument::AssocType(x) if x.ident == "Item" => Some(&x.ty),
+					_ => None,
+				})
+			})?;
+			let mut types = type_args(type_result_args(item)?);
+			Some(MappedStreamItem {
+				row: types.next()?,
+				error: types.next()?,
+				boxed: false,
+			})
+		},
+		syn::Type::Path(path) => {
+			let last = path.path.segments.last()?;
+			if last.ident != "RowStream" {
+				return None;
+			}
+			let arguments = match &last.arguments {
+				syn::PathArguments::AngleBracketed(args) => args,
+				_ => return None,
+			};
+			let mut types = type_args(arguments);
+			Some(MappedStreamItem {
+				row: types.next()?,
+				error: types.next()?,
+				boxed: true,
+			})
+		},
+		_ => None,
+	}
+}
+
+fn type_args(arguments: &syn::AngleBracketedGenericArguments) -> impl Iterator<Item = &syn::Type> {
+	arguments.args.iter().filter_map(|arg| match arg {
+		syn::GenericArgument::Type(x) => Some(x),
+		_ => None,
+	})
+}
+
 pub fn type_is_row_stream(typ: &syn::Type) -> bool {
 	let candidates = &[
 		["RowStream"].as_slice(),
@@ -132,6 +233,11 @@ pub fn type_is_row_stream(typ: &syn::Type) -> bool {
 		["RowIter"].as_slice(),
 		["postgres", "RowIter"].as_slice(),
 		["", "postgres", "RowIter"].as_slice(),
+		["BoxStream"].as_slice(),
+		["futures", "stream", "BoxStream"].as_slice(),
+		["", "futures", "stream", "BoxStream"].as_slice(),
+		["futures_core", "stream", "BoxStream"].as_slice(),
+		["", "futures_core", "stream", "BoxStream"].as_slice(),
 	];
 
 	if let Some(path) = type_as_path(typ) {
@@ -141,6 +247,57 @@ pub fn type_is_row_stream(typ: &syn::Type) -> bool {
 	}
 }
 
+// Check if a parameter type looks like a database client, connection, transaction or pool.
+//
+// Only the last path segment is checked, so the crate can be imported under any name.
+// Generic type parameters are clients if they are listed in `client_generics`.
+pub fn type_is_client(typ: &syn::Type, client_generics: &[&syn::Ident]) -> bool {
+	let well_known = &[
+		"Client", "GenericClient", "Transaction", "Savepoint",
+		"Connection", "PgConnection", "SqliteConnection", "MySqlConnection", "AnyConnection", "PoolConnection",
+		"Pool", "PgPool", "SqlitePool", "MySqlPool", "AnyPool",
+	];
+
+	match type_strip_paren(typ) {
+		syn::Type::Reference(reference) => type_is_client(&reference.elem, client_generics),
+		syn::Type::Path(typ) => match (typ.path.get_ident(), typ.path.segments.last()) {
+			(Some(ident), _) if client_generics.contains(&ident) => true,
+			(_, Some(last)) => well_known.iter().any(|name| last.ident == name),
+			(_, None) => false,
+		},
+		syn::Type::ImplTrait(typ) => typ.bounds.iter().any(bound_is_generic_client),
+		_ => false,
+	}
+}
+
+// Get the generic type parameters that are bound by a `GenericClient` trait.
+pub fn generic_client_params(generics: &syn::Generics) -> Vec<&syn::Ident> {
+	let mut output = Vec::new();
+	for param in generics.type_params() {
+		if param.bounds.iter().any(bound_is_generic_client) {
+			output.push(&param.ident);
+		}
+	}
+	for predicate in generics.where_clause.iter().flat_map(|x| &x.predicates) {
+		if let syn::WherePredicate::Type(predicate) = predicate {
+			let ident = type_as_path(&predicate.bounded_ty).and_then(|path| path.get_ident());
+			if let Some(ident) = ident {
+				if predicate.bounds.iter().any(bound_is_generic_client) && !output.contains(&ident) {
+					output.push(ident);
+				}
+			}
+		}
+	}
+	output
+}
+
+fn bound_is_generic_client(bound: &syn::TypeParamBound) -> bool {
+	match bound {
+		syn::TypeParamBound::Trait(bound) => bound.path.segments.last().is_some_and(|last| last.ident == "GenericClient"),
+		_ => false,
+	}
+}
+
 pub fn type_is_unit(typ: &syn::Type) -> bool {
 	if let syn::Type::Tuple(tuple) = type_strip_paren(typ) {
 		tuple.elems.is_empty()
@@ -149,6 +306,57 @@ pub fn type_is_unit(typ: &syn::Type) -> bool {
 	}
 }
 
+pub fn type_tuple_elems(typ: &syn::Type) -> Option<&syn::punctuated::Punctuated<syn::Type, syn::token::Comma>> {
+	match type_strip_paren(typ) {
+		syn::Type::Tuple(tuple) if !tuple.elems.is_empty() => Some(&tuple.elems),
+		_ => None,
+	}
+}
+
+pub fn type_is_scalar(typ: &syn::Type) -> bool {
+	let path = match type_as_path(typ) {
+		Some(x) => x,
+		None => return false,
+	};
+
+	let primitives = [
+		"bool", "char",
+		"i8", "i16", "i32", "i64", "i128",
+		"u16", "u32", "u128",
+		"f32", "f64",
+		"String",
+	];
+	if primitives.iter().any(|name| path.is_ident(name)) {
+		return true;
+	}
+
+	let candidates = &[
+		["std", "string", "String"].as_slice(),
+		["alloc", "string", "String"].as_slice(),
+		["", "std", "string", "String"].as_slice(),
+		["", "alloc", "string", "String"].as_slice(),
+		["serde_json", "Value"].as_slice(),
+		["", "serde_json", "Value"].as_slice(),
+	];
+	if path_is_one_of(path, candidates) {
+		return true;
+	}
+
+	// Well-known types from other crates, matched only on the last path segment.
+	let well_known = [
+		"Uuid",
+		"Decimal",
+		"NaiveDate", "NaiveTime", "NaiveDateTime", "DateTime",
+		"OffsetDateTime", "PrimitiveDateTime",
+		"SystemTime",
+		"IpAddr", "Ipv4Addr", "Ipv6Addr",
+	];
+	match path.segments.last() {
+		Some(last) => well_known.iter().any(|name| last.ident == name),
+		None => false,
+	}
+}
+
 pub fn type_is_u64(typ: &syn::Type) -> bool {
 	match type_as_path(typ) {
 		None => false,
@@ -233,3 +441,52 @@ pub fn return_type_err_span(signature: &syn::Signature) -> proc_macro2::Span {
 		}
 	}
 }
+
+// The edit distance between two strings, counted in characters.
+//
+// Swapping two adjacent characters counts as a single edit, since that is a common typo.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+	let mut before_previous: Vec<usize> = Vec::new();
+	let mut previous: Vec<usize> = (0..=b.len()).collect();
+	for i in 0..a.len() {
+		let mut current = vec![i + 1];
+		for j in 0..b.len() {
+			let substitute = previous[j] + usize::from(a[i] != b[j]);
+			let mut distance = substitute.min(previous[j + 1] + 1).min(current[j] + 1);
+			if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+				distance = distance.min(before_previous[j - 1] + 1);
+			}
+			current.push(distance);
+		}
+		before_previous = std::mem::replace(&mut previous, current);
+	}
+	previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn edit_distance_of_empty_strings() {
+		assert_eq!(edit_distance("", ""), 0);
+		assert_eq!(edit_distance("", "name"), 4);
+		assert_eq!(edit_distance("name", ""), 4);
+	}
+
+	#[test]
+	fn edit_distance_counts_transpositions_once() {
+		assert_eq!(edit_distance("ab", "ba"), 1);
+		assert_eq!(edit_distance("species", "sepcies"), 1);
+		assert_eq!(edit_distance("abc", "ca"), 3);
+	}
+
+	#[test]
+	fn edit_distance_counts_characters() {
+		assert_eq!(edit_distance("kitten", "sitting"), 3);
+		assert_eq!(edit_distance("café", "cafe"), 1);
+		assert_eq!(edit_distance("name", "name"), 0);
+	}
+}
@@ -6,3 +6,5 @@
 [`tokio_postgres`]: https://docs.rs/tokio-postgres
 [`Vec`]: https://doc.rust-lang.org/stable/std/vec/struct.Vec.html
 [`Option`]: https://doc.rust-lang.org/stable/std/option/enum.Option.html
+[`postgres`]: https://docs.rs/postgres
+[`mod@serde`]: https://docs.rs/inline-sql/latest/inline_sql/serde/index.html
@@ -23,7 +23,7 @@ async fn add_pet(client: &tokio_postgres::Client, name: &str, species: &str) ->
 	query!(INSERT INTO pets (name, species) VALUES ($name, $species))
 }
 
-#[derive(pg_mapper::TryFromRow)]
+#[derive(inline_sql::FromRow)]
 #[derive(Debug)]
 struct Pet {
 	name: String,
@@ -0,0 +1,5 @@
+-- Get all pets of a species, ordered by name.
+SELECT *
+FROM pets
+WHERE species = $species
+ORDER BY name
@@ -0,0 +1,7 @@
+-- name: get_pet_by_name
+SELECT * FROM pets WHERE name = $name
+
+-- name: rename_species
+UPDATE pets
+SET species = $new_species
+WHERE species = $old_species
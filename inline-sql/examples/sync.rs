@@ -0,0 +1,109 @@
+use inline_sql::inline_sql;
+use clap::CommandFactory;
+
+#[inline_sql]
+fn create_table(client: &mut postgres::Client) -> Result<(), postgres::Error> {
+	query!(CREATE TABLE pets (
+		name TEXT PRIMARY KEY,
+		species TEXT NOT NULL
+	))
+}
+
+#[inline_sql]
+fn get_pets(client: &mut postgres::Client) -> Result<Vec<Pet>, postgres::Error> {
+	query!(SELECT * FROM pets)
+}
+
+#[inline_sql]
+fn get_pet_by_name(client: &mut postgres::Client, name: &str) -> Result<Option<Pet>, postgres::Error> {
+	query!(SELECT * FROM pets WHERE name = $name)
+}
+
+#[inline_sql]
+fn add_pet(client: &mut postgres::Transaction, name: &str, species: &str) -> Result<u64, postgres::Error> {
+	query!(INSERT INTO pets (name, species) VALUES ($name, $species))
+}
+
+#[derive(inline_sql::FromRow)]
+#[derive(Debug)]
+struct Pet {
+	name: String,
+	species: String,
+}
+
+#[derive(clap::Parser)]
+struct Options {
+	#[clap(long, short)]
+	#[clap(global = true)]
+	url: Option<String>,
+
+	#[clap(subcommand)]
+	command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+	CreateTable,
+	GetPets,
+	GetPet {
+		name: String,
+	},
+	AddPet {
+		name: String,
+		species: String,
+	},
+}
+
+fn main() {
+	if let Err(()) = do_main(clap::Parser::parse()) {
+		std::process::exit(1);
+	}
+}
+
+fn do_main(options: Options) -> Result<(), ()> {
+	let url = options.url
+		.ok_or_else(|| {
+			clap::error::Error::<clap::error::RichFormatter>::raw(
+				clap::error::ErrorKind::MissingRequiredArgument,
+				"the following required argument is missing: --url URL\n",
+			)
+			.with_cmd(&Options::command())
+			.print()
+			.ok();
+		})?;
+
+	let mut client = postgres::Client::connect(&url, postgres::NoTls)
+		.map_err(|e| eprintln!("Failed to connect to {url}: {e}"))?;
+
+	match options.command {
+		Command::CreateTable => {
+			create_table(&mut client)
+				.map_err(|e| eprintln!("Failed to create table: {e}"))?;
+		},
+		Command::GetPets => {
+			let pets = get_pets(&mut client)
+				.map_err(|e| eprintln!("Failed to get pets: {e}"))?;
+			for pet in &pets {
+				println!("Name: {}, species: {}", pet.name, pet.species);
+			}
+			println!("Total: {}", pets.len());
+		},
+		Command::GetPet { name } => {
+			let pet = get_pet_by_name(&mut client, &name)
+				.map_err(|e| eprintln!("Failed to get pet: {e}"))?
+				.ok_or_else(|| eprintln!("No pet found with name: {name:?}" ))?;
+			println!("{pet:#?}");
+		},
+		Command::AddPet { name, species } => {
+			let mut transaction = client.transaction()
+				.map_err(|e| eprintln!("Failed to start transaction: {e}"))?;
+			let count = add_pet(&mut transaction, &name, &species)
+				.map_err(|e| eprintln!("Failed to insert pet: {e}"))?;
+			transaction.commit()
+				.map_err(|e| eprintln!("Failed to commit transaction: {e}"))?;
+			println!("Inserted {count} rows" );
+		},
+	}
+
+	Ok(())
+}
@@ -6,11 +6,26 @@
 //!
 //! See the documentation of the [`#[inline_sql]`][`inline_sql`] macro for more details and examples.
 //!
-//! Currently, only [`tokio-postgres`][`tokio_postgres`] is supported as backend.
+//! Currently, [`tokio-postgres`][`tokio_postgres`] and [`sqlx`][`sqlx`] are supported as backend for `async` functions,
+//! and [`postgres`][`postgres`] and [`rusqlite`][`rusqlite`] are supported as backend for synchronous functions.
+//!
+//! # Features
+//! Each backend has a cargo feature with the same name: `tokio-postgres`, `postgres`, `rusqlite` and `sqlx`.
+//! Only the `tokio-postgres` feature is enabled by default.
+//!
+//! The features enable the runtime support for a backend, but they do not change the default backend:
+//! `async` functions always use `tokio_postgres` and synchronous functions always use `postgres` by default.
+//! To use `rusqlite` or `sqlx`, enable its feature and select it with the `backend` argument of the [`#[inline_sql]`][`inline_sql`] macro.
+//! Selecting one of these backends without its feature is reported as an error.
+//! This also means that a single crate can mix different backends.
+//! If you do not use `tokio-postgres`, you can disable the default features to avoid pulling it in as dependency.
+//!
+//! The `serde` feature adds the [`serde`][`mod@serde`] module, to deserialize rows of the `tokio-postgres` and `postgres` backends
+//! into any type that implements `serde::Deserialize`, with the `map_row = serde` argument.
 //!
 //! # Example: Return a [`Vec`] of rows.
 //! ```
-//! # #[derive(pg_mapper::TryFromRow)]
+//! # #[derive(inline_sql::FromRow)]
 //! # struct Pet {
 //! #   name: String,
 //! #   species: String,
@@ -27,10 +42,10 @@
 //! ```
 //!
 //! # Planned features:
-//! * Support for queries that return exactly one row or an error.
-//! * Specifying the query type and row type as macro arguments instead of deducing it from the return type.
-//! * Parsing the function arguments to determine the name of the `client` object.
-//! * Support for more backends, including synchronous backends.
+//! * Support for more backends.
+//!
+//! [`rusqlite`]: https://docs.rs/rusqlite
+//! [`sqlx`]: https://docs.rs/sqlx
 
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
@@ -40,6 +55,29 @@
 ///
 /// The function body must follow the form
 /// `query! { ... }` or `query!(...)`.
+/// You can refer to function parameters in the query with `$name`.
+///
+/// Placeholders can also be field accesses like `$filter.species` or `$self.tenant_id`,
+/// or arbitrary expressions in parentheses or braces like `$(page * size)` or `${name}`.
+/// Identical placeholders are sent to the database as a single parameter.
+///
+/// Placeholder names are checked against the function parameters,
+/// so a typo like `$specis` is reported with a suggestion for the closest parameter name.
+/// Names starting with an uppercase letter are assumed to be constants and are not checked,
+/// and neither are expressions in parentheses or braces.
+/// Parameters that are not used in the query cause a warning,
+/// unless their name starts with an underscore or they are used in the `client`, `map_row`, `map_err` or `group_by` argument.
+///
+/// The query inside the macro is tokenized by the Rust lexer,
+/// so it can not contain single-quoted SQL strings or `--` comments.
+/// The macro reconstructs the whitespace and line breaks between the tokens from their source location,
+/// so the query sent to the database looks like the query in your source code.
+/// Indentation is kept relative to the least indented line, and tabs are copied from the source code.
+/// Only for tokens from other macros, like `macro_rules!` fragments, tabs are replaced by a single space.
+/// On compilers that do not provide source locations to procedural macros, all tokens are separated by a single space instead.
+/// You can also give the query as a single string literal, like `query!("SELECT ...")` or `query!(r#"SELECT ..."#)`.
+/// The string is sent to the database as-is, except for the `$name` placeholders.
+/// Placeholders inside string literals, quoted identifiers, comments and dollar-quoted strings are left untouched.
 ///
 /// The return type of the function determines the behavior of the function.
 /// There are a few options for the return type:
@@ -47,17 +85,103 @@
 /// * [`Result`]`<`[`u64`]`, E>`: Execute the query and return the number of affected rows.
 /// * [`Result`]`<`[`Vec`]`<T>, E>`: Execute the query and return the rows as a vector.
 /// * [`Result`]`<`[`Option`]`<T>, E>`: Execute the query and return a single optional row.
+/// * [`Result`]`<T, E>`: Execute the query and return exactly one row.
+/// * [`Result`]`<C, E>`: Execute the query and collect the rows into a well-known collection `C`, such as
+///   [`VecDeque`][std::collections::VecDeque]`<T>`, [`HashSet`][std::collections::HashSet]`<T>`, [`BTreeSet`][std::collections::BTreeSet]`<T>`,
+///   [`HashMap`][std::collections::HashMap]`<K, V>`, [`BTreeMap`][std::collections::BTreeMap]`<K, V>`, `IndexMap<K, V>` or [`Box`]`<[T]>`.
 /// * [`Result`]`<`[`RowStream`][`tokio_postgres::RowStream`]`, E>`: Execute the query and return a [`RowStream`][`tokio_postgres::RowStream`].
+/// * [`Result`]`<`[`RowIter`][`postgres::RowIter`]`, E>`: Execute the query and return a [`RowIter`][`postgres::RowIter`] (synchronous functions only).
+/// * [`Result`]`<impl `[`Stream`][`futures::Stream`]`<Item = `[`Result`]`<T, E>>, E>`: Execute the query and return a stream of converted rows (`async` functions only).
+/// * [`Result`]`<`[`RowStream`]`<T, E>, E>`: Execute the query and return a boxed stream of converted rows (`async` functions only).
 ///
 /// The row type `T` must implement [`TryFrom<`][TryFrom][`tokio_postgres::Row`]`>`.
 /// The [`TryFrom::Error`] type must implement [`Into<E>`].
 ///
 /// The error type `E` must implement [`From<`][From][`tokio_postgres::Error`]>`.
 ///
+/// If the row type `T` is a well-known scalar type, the first column of each row is decoded directly instead.
+/// This is the case for the primitive numeric types (except [`u8`] and [`u64`]), [`bool`], [`char`], [`String`],
+/// and a few common types by name, such as `Uuid`, `Decimal`, `NaiveDate`, `DateTime`, `IpAddr` and `serde_json::Value`.
+/// So a function returning `Result<i64, E>`, `Result<Option<String>, E>` or `Result<Vec<Uuid>, E>` does not need a wrapper struct.
+/// Note that a single scalar like `Result<i64, E>` is still exactly one row, so `E` must implement [`From<`][From][`RowCountError`]`>`.
+/// A plain [`tokio_postgres::Error`] or [`postgres::Error`] does not, so use your own error type as shown in the example below.
+/// For other types, you can add the `scalar` argument to the attribute (see below).
+/// Scalar values are decoded with [`Row::try_get()`][`tokio_postgres::Row::try_get`], so the type must implement [`FromSql`][`tokio_postgres::types::FromSql`].
+///
+/// If the row type `T` is a tuple, like `(String, i32, Option<Date>)`, each element is decoded from the column at the same position.
+/// The query must return exactly as many columns as the tuple has elements, otherwise a [`ColumnCountError`] is reported.
+/// The error type `E` must implement [`From<`][From][`ColumnCountError`]`>` for these functions.
+/// A plain [`tokio_postgres::Error`] or [`postgres::Error`] does not, so even an ad-hoc join like `Result<Vec<(String, i32)>, E>`
+/// needs your own error type, as shown in the example below.
+///
+/// For functions that return a stream of converted rows, each row is converted when the stream is polled,
+/// and the `map_row` and `map_err` arguments are applied to each item of the stream.
+/// This allows you to process large query results without buffering all rows in memory.
+///
+/// When collecting into a map, the key `K` is decoded from the first column of each row.
+/// If the value `V` is a scalar type, it is decoded from the second column and the query must return exactly two columns.
+/// Otherwise, the value is converted from the entire row, so the key column can also be a field of the value.
+/// Other collections can be used with the `kind = collect` argument (see below), as long as they implement [`FromIterator`].
+///
 /// For functions that return a `Result<Option<T>, E>`, an error is reported if the query returned more than one row.
 ///
+/// For functions that return a `Result<T, E>`, a [`RowCountError`] is reported if the query returned no rows or more than one row.
+/// The error type `E` must also implement [`From<`][From][`RowCountError`]`>` for these functions.
+///
+/// If the function is `async`, the generated code uses [`tokio_postgres`].
+/// The `client` must be a [`tokio_postgres::Client`] or [`tokio_postgres::Transaction`].
+///
+/// If the function is not `async`, the generated code uses the blocking [`postgres`] crate instead.
+/// The `client` must then be a `&mut` [`postgres::Client`] or `&mut` [`postgres::Transaction`].
+/// Note that [`postgres::Row`] is the same type as [`tokio_postgres::Row`] and [`postgres::Error`] is the same type as [`tokio_postgres::Error`].
+///
+/// You can also use [`rusqlite`] by enabling the `rusqlite` feature and adding the `backend = rusqlite` argument to the attribute (see below).
+/// The function must not be `async`, and the `client` must be a [`rusqlite::Connection`] or [`rusqlite::Transaction`].
+/// The row type `T` must implement [`TryFrom<`][TryFrom]`&`[`rusqlite::Row`]`>` instead,
+/// and the error type `E` must implement [`From<`][From][`rusqlite::Error`]`>`.
+/// Placeholders are rendered as `?1`, `?2`, etc. in the query sent to SQLite.
+/// Statements are prepared with [`rusqlite::Connection::prepare_cached()`].
+/// The `rusqlite` backend does not support returning a row stream,
+/// and for functions that return a `Result<Option<T>, E>`, additional rows are ignored.
+///
+/// You can use [`sqlx`] by enabling the `sqlx` feature and adding the `backend = sqlx` argument to the attribute.
+/// The function must be `async`, and the `client` can be any [`sqlx::Executor`], such as a `&`[`sqlx::PgPool`], `&`[`sqlx::SqlitePool`] or `&mut `[`sqlx::PgConnection`].
+/// The row type `T` must implement [`sqlx::FromRow`], and the error type `E` must implement [`From<`][From][`sqlx::Error`]`>`.
+/// Parameters are bound with [`sqlx::query::Query::bind()`].
+/// To return a stream of rows, use [`BoxStream`][`futures::stream::BoxStream`]`<'_, `[`Result`]`<Row, `[`sqlx::Error`]`>>` as the success type.
+/// In that case, the query parameters are cloned and bound by value, since the stream outlives the function body.
+/// A stream of converted rows borrows the `client`, so use `impl Stream<Item = Result<T, E>> + '_` as the success type.
+/// For functions that return a `Result<Option<T>, E>`, additional rows are ignored.
+///
 /// You can generally not use a type alias in the return type of the function.
 /// The proc macro can not resolve the alias, and will not know which variant to generate.
+/// Note that any return type not listed above is treated as a single row,
+/// so an alias for a [`Vec`] or [`Option`] will be interpreted as a row type.
+/// If you want to use a type alias, you can specify the query kind and row type with the `kind` and `row` arguments (see below).
+///
+/// # Impl blocks
+///
+/// You can also place the attribute on an `impl` block.
+/// Every method with a `query!` body is then treated as an SQL function, and other items are left untouched.
+/// The `client`, `map_err` and `backend` arguments can be given on the `impl` block, and they apply to all those methods.
+/// A method can override them with its own `#[inline_sql(...)]` attribute.
+/// Other arguments describe a single query, so they can only be given on a method,
+/// which is also needed for methods that load their query from a file.
+///
+/// # Traits
+///
+/// Placed on a trait, the attribute turns every method with a `query!` body into a required method,
+/// and implements the trait for every [`GenericClient`][`tokio_postgres::GenericClient`] of the backend.
+/// Inside these methods, `self` is the client.
+/// Methods without a `self` receiver find their client in the function parameters, like free functions.
+/// Every other method of the trait needs a default implementation, since the generated implementation only provides the SQL functions.
+/// Other implementations of the trait, such as in-memory fakes for tests, can still be written by hand.
+/// Like for `impl` blocks, only the `client`, `map_err`, `backend` and `impl_for` arguments can be given on the trait.
+///
+/// The `tokio_postgres` and `postgres` backends have a generic client trait.
+/// For other backends, or to implement the trait for a single client type, use the `impl_for` argument.
+/// The client trait follows the `backend` argument of the trait, or else the backend of the methods.
+/// Without `impl_for`, all methods must use the same backend, so async and non-async methods can not be mixed.
 ///
 /// # Macro arguments
 ///
@@ -70,6 +194,12 @@
 /// Specify the SQL client object to use.
 /// The argument value must be an expression that gives a [`tokio_postgres::Client`], preferably by reference.
 ///
+/// If this argument is not given, the client is determined from the function parameters.
+/// A parameter named `client` is always used, regardless of its type.
+/// Otherwise, a parameter with a client type like `&Client`, `&Transaction`, `&Connection`, `&impl GenericClient` or a pool is used.
+/// This includes generic parameters like `db: &C` where `C: GenericClient`.
+/// If there is no such parameter, `self.client` is used for methods.
+///
 /// #### `#[inline_sql(map_row = ...)]`
 ///
 /// Specify a custom function to convert a row from the query result to the return value.
@@ -77,6 +207,12 @@
 ///
 /// You can specify the name of a function or a closure.
 ///
+/// With the `serde` feature, you can use `map_row = serde` to deserialize rows into any type that implements `serde::Deserialize`,
+/// using the column names as field names.
+/// See the [`serde`][`mod@serde`] module for details.
+/// The error type `E` must implement [`From<`][From]`inline_sql::serde::Error>`.
+/// This is only supported for the `tokio_postgres` and `postgres` backends.
+///
 /// #### `#[inline_sql(map_err = ...)]`
 ///
 /// Specify a custom function to convert the SQL error to the error from the function return type.
@@ -84,6 +220,97 @@
 ///
 /// You can specify the name of a function or a closure.
 ///
+/// #### `#[inline_sql(group_by = ...)]`
+///
+/// Fold consecutive rows with the same parent into a parent and a [`Vec`] of children, like for a `JOIN` of a one-to-many relation.
+/// The argument value must be an expression that gives a function with the signature `Fn(&P) -> K`, where `K` implements [`PartialEq`].
+///
+/// The function must return a `Result<Vec<(P, Vec<C>)>, E>`.
+/// To return another element type that implements [`From<`][From]`(P, Vec<C>)>`, use the `kind = list` and `row = (P, C)` arguments.
+///
+/// Both the parent `P` and the child `C` are converted from the entire row with [`FromRow`].
+/// Rows are grouped while they are read, so the query should order the rows by the parent key.
+/// If all columns of the child are NULL, like for a `LEFT JOIN` without a match, the parent gets no child for that row.
+/// With the `map_row` argument, the function must return a `(P, C)` tuple for each row.
+/// The `sqlx` backend requires the `map_row` argument, since [`sqlx::FromRow`] can not detect a missing child.
+///
+/// #### `#[inline_sql(backend = ...)]`
+///
+/// Specify the backend to generate code for.
+/// The argument value must be one of `tokio_postgres`, `postgres`, `rusqlite` or `sqlx`.
+///
+/// If not specified, `async` functions use `tokio_postgres` and synchronous functions use `postgres`.
+/// The default backend does not depend on the enabled cargo features, see the [crate documentation](crate#features) for details.
+/// The `rusqlite` and `sqlx` backends can only be selected if the cargo feature with the same name is enabled.
+///
+/// #### `#[inline_sql(kind = ...)]`
+///
+/// Specify the kind of query instead of deducing it from the return type.
+/// The argument value must be one of:
+/// * `execute`: Execute the query without returning anything, like `Result<(), E>`.
+/// * `count`: Execute the query and return the number of affected rows, like `Result<u64, E>`.
+/// * `list`: Return all rows as a vector, like `Result<Vec<T>, E>`.
+/// * `optional`: Return a single optional row, like `Result<Option<T>, E>`.
+/// * `one`: Return exactly one row, like `Result<T, E>`.
+/// * `collect`: Collect the rows into any type that implements [`FromIterator`], like `Result<HashSet<T>, E>`.
+/// * `stream`: Return the raw row stream of the backend, like `Result<RowStream, E>`.
+///
+/// This allows you to use type aliases in the return type.
+/// The function must still return a [`Result`] (or an alias for it).
+///
+/// #### `#[inline_sql(row = ...)]`
+///
+/// Specify the row type for the `list`, `optional`, `one` and `collect` query kinds.
+/// This argument can only be used together with the `kind` argument, and not with the `execute`, `count` and `stream` query kinds.
+///
+/// If not specified, the macro attempts to deduce the row type from the return type.
+/// If the return type is an alias, you must specify the row type explicitly.
+///
+/// #### `#[inline_sql(scalar)]`
+///
+/// Decode the first column of each row as the row type, instead of converting the whole row.
+/// This argument does not take a value.
+///
+/// You only need this for types that are not recognized as scalar type automatically,
+/// such as your own types that implement [`FromSql`][`tokio_postgres::types::FromSql`].
+///
+/// #### `#[inline_sql(file = "...")]`
+///
+/// Load the query from an SQL file instead of the function body.
+/// The path is relative to the directory containing the `Cargo.toml` of your crate.
+///
+/// The file is read as-is, just like a query given as a string literal, so it can use `$name` placeholders for the function parameters.
+/// The function body must be empty.
+/// Changes to the file cause your crate to be recompiled.
+///
+/// #### `#[inline_sql(file = "...", name = "...")]`
+///
+/// Select a named query from an SQL file that contains multiple queries.
+/// Each query in the file must be preceded by a header comment of the form `-- name: <name>`.
+/// The query runs until the next header or the end of the file.
+/// Each name can only be used once in a file.
+///
+/// This argument can only be used together with the `file` argument.
+///
+/// #### `#[inline_sql(placeholder = ':')]`
+///
+/// Use a different sigil for placeholders: one of `'$'` (the default), `':'` or `'@'`.
+///
+/// A `$` can not be written in the body of a `macro_rules!` macro,
+/// so this allows you to generate functions with a macro.
+/// Fragments passed to the macro can be used in the query and as placeholders.
+/// With the `':'` sigil, `::` is still parsed as a type cast.
+///
+/// #### `#[inline_sql(impl_for = ...)]`
+///
+/// Implement a trait for the given client type, instead of for every generic client of the backend.
+/// This argument can only be used on a trait.
+///
+/// #### `#[inline_sql(allow_unused)]`
+///
+/// Do not warn about function parameters that are not used in the query.
+/// This argument does not take a value.
+///
 /// # Example 1: Ignore the query output.
 /// ```
 /// use inline_sql::inline_sql;
@@ -105,7 +332,7 @@
 /// ```
 /// use inline_sql::inline_sql;
 ///
-/// # #[derive(pg_mapper::TryFromRow)]
+/// # #[derive(inline_sql::FromRow)]
 /// # struct Pet {
 /// #   name: String,
 /// #   species: String,
@@ -124,7 +351,7 @@
 /// ```
 /// use inline_sql::inline_sql;
 ///
-/// # #[derive(pg_mapper::TryFromRow)]
+/// # #[derive(inline_sql::FromRow)]
 /// # struct Pet {
 /// #   name: String,
 /// #   species: String,
@@ -139,11 +366,48 @@
 /// }
 /// ```
 ///
+/// # Example: Return exactly one row.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[derive(Debug)]
+/// enum Error {
+///   Sql(tokio_postgres::Error),
+///   RowCount(inline_sql::RowCountError),
+/// }
+///
+/// impl From<tokio_postgres::Error> for Error {
+///   fn from(other: tokio_postgres::Error) -> Self {
+///     Self::Sql(other)
+///   }
+/// }
+///
+/// impl From<inline_sql::RowCountError> for Error {
+///   fn from(other: inline_sql::RowCountError) -> Self {
+///     Self::RowCount(other)
+///   }
+/// }
+///
+/// #[inline_sql]
+/// async fn get_pet_by_name(
+///   client: &tokio_postgres::Client,
+///   name: &str,
+/// ) -> Result<Pet, Error> {
+///     query!(SELECT * FROM pets WHERE name = $name)
+/// }
+/// ```
+///
 /// # Example: Return the number of affected rows.
 /// ```
 /// use inline_sql::inline_sql;
 ///
-/// # #[derive(pg_mapper::TryFromRow)]
+/// # #[derive(inline_sql::FromRow)]
 /// # struct Pet {
 /// #   name: String,
 /// #   species: String,
@@ -158,18 +422,946 @@
 ///     query!(UPDATE pets SET species = #new_species WHERE species = #old_species)
 /// }
 /// ```
+///
+/// # Example: Return a single column.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # type Species = String;
+/// #
+/// // A single scalar is exactly one row, so the error type must also accept a `RowCountError`.
+/// #[derive(Debug)]
+/// enum Error {
+///   Sql(tokio_postgres::Error),
+///   RowCount(inline_sql::RowCountError),
+/// }
+///
+/// impl From<tokio_postgres::Error> for Error {
+///   fn from(other: tokio_postgres::Error) -> Self {
+///     Self::Sql(other)
+///   }
+/// }
+///
+/// impl From<inline_sql::RowCountError> for Error {
+///   fn from(other: inline_sql::RowCountError) -> Self {
+///     Self::RowCount(other)
+///   }
+/// }
+///
+/// #[inline_sql]
+/// async fn count_pets(client: &tokio_postgres::Client) -> Result<i64, Error> {
+///     query!(SELECT count(*) FROM pets)
+/// }
+///
+/// #[inline_sql]
+/// async fn get_pet_names(client: &tokio_postgres::Client) -> Result<Vec<String>, tokio_postgres::Error> {
+///     query!(SELECT name FROM pets ORDER BY name)
+/// }
+///
+/// #[inline_sql(scalar)]
+/// async fn get_species(client: &tokio_postgres::Client, name: &str) -> Result<Option<Species>, tokio_postgres::Error> {
+///     query!(SELECT species FROM pets WHERE name = $name)
+/// }
+/// ```
+///
+/// # Example: Return tuples.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// // The number of columns is checked, so the error type must also accept a `ColumnCountError`.
+/// #[derive(Debug)]
+/// enum Error {
+///   Sql(tokio_postgres::Error),
+///   ColumnCount(inline_sql::ColumnCountError),
+/// }
+///
+/// impl From<tokio_postgres::Error> for Error {
+///   fn from(other: tokio_postgres::Error) -> Self {
+///     Self::Sql(other)
+///   }
+/// }
+///
+/// impl From<inline_sql::ColumnCountError> for Error {
+///   fn from(other: inline_sql::ColumnCountError) -> Self {
+///     Self::ColumnCount(other)
+///   }
+/// }
+///
+/// #[inline_sql]
+/// async fn get_owners_and_pets(client: &tokio_postgres::Client) -> Result<Vec<(String, String, Option<i32>)>, Error> {
+///     query!(SELECT owners.name, pets.name, pets.age FROM owners JOIN pets ON pets.owner = owners.id)
+/// }
+/// ```
+///
+/// # Example: Collect rows into a map.
+/// ```
+/// use inline_sql::inline_sql;
+/// use std::collections::{BTreeMap, HashSet};
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   id: i32,
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[inline_sql]
+/// async fn get_pets_by_id(client: &tokio_postgres::Client) -> Result<BTreeMap<i32, Pet>, tokio_postgres::Error> {
+///     query!(SELECT id, * FROM pets)
+/// }
+///
+/// #[inline_sql]
+/// async fn get_species(client: &tokio_postgres::Client) -> Result<HashSet<String>, tokio_postgres::Error> {
+///     query!(SELECT species FROM pets)
+/// }
+/// ```
+///
+/// # Example: Group joined rows by parent.
+/// ```
+/// use inline_sql::{inline_sql, FromRow};
+///
+/// #[derive(FromRow)]
+/// struct Owner {
+///   id: i32,
+///   name: String,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Pet {
+///   #[sql(rename = "pet_name")]
+///   name: String,
+/// }
+///
+/// #[inline_sql(group_by = |owner: &Owner| owner.id)]
+/// async fn get_owners_with_pets(client: &tokio_postgres::Client) -> Result<Vec<(Owner, Vec<Pet>)>, tokio_postgres::Error> {
+///   query! {
+///     SELECT owners.id, owners.name, pets.name AS pet_name
+///     FROM owners LEFT JOIN pets ON pets.owner_id = owners.id
+///     ORDER BY owners.id
+///   }
+/// }
+/// ```
+///
+/// # Example: Return a stream of converted rows.
+/// ```
+/// use futures::{Stream, StreamExt};
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[inline_sql]
+/// async fn get_all_pets(
+///   client: &tokio_postgres::Client,
+/// ) -> Result<impl Stream<Item = Result<Pet, tokio_postgres::Error>>, tokio_postgres::Error> {
+///     query!(SELECT * FROM pets)
+/// }
+///
+/// #[inline_sql]
+/// async fn get_pet_names(
+///   client: &tokio_postgres::Client,
+/// ) -> Result<inline_sql::RowStream<String, tokio_postgres::Error>, tokio_postgres::Error> {
+///     query!(SELECT name FROM pets)
+/// }
+///
+/// async fn count_cats(client: &tokio_postgres::Client) -> Result<usize, tokio_postgres::Error> {
+///   let mut pets = std::pin::pin!(get_all_pets(client).await?);
+///   let mut cats = 0;
+///   while let Some(pet) = pets.next().await {
+///     if pet?.species == "cat" {
+///       cats += 1;
+///     }
+///   }
+///   Ok(cats)
+/// }
+/// ```
+///
+/// # Example: Write the query as a string literal.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// #[inline_sql]
+/// async fn greet_pets(
+///   client: &tokio_postgres::Client,
+///   species: &str,
+/// ) -> Result<Vec<String>, tokio_postgres::Error> {
+///     query!(r#"
+///       -- Single-quoted strings are passed through as-is.
+///       SELECT 'Hello, ' || name || '!'
+///       FROM pets
+///       WHERE species = $species
+///     "#)
+/// }
+/// ```
+///
+/// # Example: Load the query from a file.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[inline_sql(file = "examples/queries/get_pets_by_species.sql")]
+/// async fn get_pets_by_species(
+///   client: &tokio_postgres::Client,
+///   species: &str,
+/// ) -> Result<Vec<Pet>, tokio_postgres::Error> {
+/// }
+/// ```
+///
+/// # Example: Load named queries from a single file.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// // The file contains `-- name: get_pet_by_name` and `-- name: rename_species` headers.
+/// #[inline_sql(file = "examples/queries/pets.sql", name = "get_pet_by_name")]
+/// async fn get_pet_by_name(
+///   client: &tokio_postgres::Client,
+///   name: &str,
+/// ) -> Result<Option<Pet>, tokio_postgres::Error> {
+/// }
+///
+/// #[inline_sql(file = "examples/queries/pets.sql", name = "rename_species")]
+/// async fn rename_species(
+///   client: &tokio_postgres::Client,
+///   old_species: &str,
+///   new_species: &str,
+/// ) -> Result<u64, tokio_postgres::Error> {
+/// }
+/// ```
+///
+/// # Example: Use expressions as placeholders.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// struct PetFilter {
+///   species: String,
+///   page: i64,
+///   page_size: i64,
+/// }
+///
+/// #[inline_sql]
+/// async fn find_pets(
+///   client: &tokio_postgres::Client,
+///   filter: &PetFilter,
+/// ) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///     query! {
+///       SELECT * FROM pets
+///       WHERE species = $filter.species
+///       ORDER BY name
+///       LIMIT $filter.page_size
+///       OFFSET $(filter.page * filter.page_size)
+///     }
+/// }
+/// ```
+///
+/// # Example: Generate functions with `macro_rules!`.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// macro_rules! delete_from {
+///   ($name:ident, $table:ident) => {
+///     #[inline_sql(placeholder = ':')]
+///     async fn $name(
+///       client: &tokio_postgres::Client,
+///       min_id: i32,
+///     ) -> Result<u64, tokio_postgres::Error> {
+///       query!(DELETE FROM $table WHERE id >= :min_id::int4)
+///     }
+///   };
+/// }
+///
+/// delete_from!(delete_pets, pets);
+/// delete_from!(delete_owners, owners);
+/// ```
+///
+/// # Example: Implement a repository with an `impl` block.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// struct PetRepo {
+///   db: tokio_postgres::Client,
+/// }
+///
+/// #[inline_sql(client = self.db)]
+/// impl PetRepo {
+///   pub async fn get_pets_by_species(&self, species: &str) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///     query!(SELECT * FROM pets WHERE species = $species)
+///   }
+///
+///   #[inline_sql(file = "examples/queries/pets.sql", name = "rename_species")]
+///   pub async fn rename_species(&self, old_species: &str, new_species: &str) -> Result<u64, tokio_postgres::Error> {}
+///
+///   pub fn client(&self) -> &tokio_postgres::Client {
+///     &self.db
+///   }
+/// }
+/// ```
+///
+/// # Example: Define a repository trait.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[inline_sql]
+/// trait PetRepo {
+///   async fn get_pets_by_species(&self, species: &str) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///     query!(SELECT * FROM pets WHERE species = $species)
+///   }
+/// }
+///
+/// // An in-memory implementation for tests.
+/// struct FakePetRepo {
+///   pets: Vec<(String, String)>,
+/// }
+///
+/// impl PetRepo for FakePetRepo {
+///   async fn get_pets_by_species(&self, species: &str) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///     let pets = self.pets.iter()
+///       .filter(|(_, pet_species)| pet_species == species)
+///       .map(|(name, species)| Pet { name: name.clone(), species: species.clone() })
+///       .collect();
+///     Ok(pets)
+///   }
+/// }
+///
+/// async fn count_dogs(repo: &impl PetRepo) -> Result<usize, tokio_postgres::Error> {
+///   Ok(repo.get_pets_by_species("dog").await?.len())
+/// }
+///
+/// async fn count_dogs_in_database(client: &tokio_postgres::Client) -> Result<usize, tokio_postgres::Error> {
+///   count_dogs(client).await
+/// }
+/// ```
+///
+/// # Example: Mix methods with and without a receiver in a trait.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// #[inline_sql]
+/// trait PetAdmin {
+///   // The client is `self`.
+///   async fn delete_pet(&self, name: &str) -> Result<u64, tokio_postgres::Error> {
+///     query!(DELETE FROM pets WHERE name = $name)
+///   }
+///
+///   // The client is the `client` parameter.
+///   async fn delete_species(client: &tokio_postgres::Client, species: &str) -> Result<u64, tokio_postgres::Error> {
+///     query!(DELETE FROM pets WHERE species = $species)
+///   }
+///
+///   // Other methods need a default implementation.
+///   async fn delete_pets(&self, names: &[&str]) -> Result<u64, tokio_postgres::Error> {
+///     let mut deleted = 0;
+///     for name in names {
+///       deleted += self.delete_pet(name).await?;
+///     }
+///     Ok(deleted)
+///   }
+/// }
+/// ```
+///
+/// # Example: Accept both a client and a transaction.
+/// ```
+/// use inline_sql::inline_sql;
+/// use tokio_postgres::GenericClient;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// #[inline_sql]
+/// async fn get_pets_by_species(
+///   client: &impl GenericClient,
+///   species: &str,
+/// ) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///     query!(SELECT * FROM pets WHERE species = $species)
+/// }
+///
+/// #[inline_sql]
+/// async fn rename_species<C: GenericClient>(
+///   db: &C,
+///   old: &str,
+///   new: &str,
+/// ) -> Result<u64, tokio_postgres::Error> {
+///     query!(UPDATE pets SET species = $new WHERE species = $old)
+/// }
+///
+/// async fn rename_and_list(client: &mut tokio_postgres::Client) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///   let transaction = client.transaction().await?;
+///   rename_species(&transaction, "dog", "wolf").await?;
+///   let pets = get_pets_by_species(&transaction, "wolf").await?;
+///   transaction.commit().await?;
+///   Ok(pets)
+/// }
+/// ```
+///
+/// # Example: Use a type alias in the return type.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+/// #
+/// type DbResult<T> = Result<T, tokio_postgres::Error>;
+///
+/// #[inline_sql(kind = list, row = Pet)]
+/// async fn get_pets_by_species(
+///   client: &tokio_postgres::Client,
+///   species: &str,
+/// ) -> DbResult<Vec<Pet>> {
+///     query!(SELECT * FROM pets WHERE species = $species)
+/// }
+///
+/// #[inline_sql(kind = count)]
+/// async fn delete_pets_by_species(
+///   client: &tokio_postgres::Client,
+///   species: &str,
+/// ) -> DbResult<u64> {
+///     query!(DELETE FROM pets WHERE species = $species)
+/// }
+/// ```
+///
+/// # Example: Use the synchronous [`postgres`] crate.
+/// ```
+/// use inline_sql::inline_sql;
+///
+/// # #[derive(inline_sql::FromRow)]
+/// # struct Pet {
+/// #   name: String,
+/// #   species: String,
+/// # }
+///
+/// #[inline_sql]
+/// fn get_pets_by_species(
+///   client: &mut postgres::Client,
+///   species: &str,
+/// ) -> Result<Vec<Pet>, postgres::Error> {
+///     query!(SELECT * FROM pets WHERE species = $species)
+/// }
+///
+/// #[inline_sql]
+/// fn iter_pets<'a>(
+///   client: &'a mut postgres::Client,
+/// ) -> Result<postgres::RowIter<'a>, postgres::Error> {
+///     query!(SELECT * FROM pets)
+/// }
+/// ```
+///
+/// # Example: Use [`rusqlite`].
+#[cfg_attr(feature = "rusqlite", doc = "```")]
+#[cfg_attr(not(feature = "rusqlite"), doc = "```ignore")]
+/// use inline_sql::inline_sql;
+///
+/// struct Pet {
+///   name: String,
+///   species: String,
+/// }
+///
+/// impl TryFrom<&rusqlite::Row<'_>> for Pet {
+///   type Error = rusqlite::Error;
+///
+///   fn try_from(row: &rusqlite::Row<'_>) -> Result<Self, Self::Error> {
+///     Ok(Self {
+///       name: row.get("name")?,
+///       species: row.get("species")?,
+///     })
+///   }
+/// }
+///
+/// #[inline_sql(backend = rusqlite)]
+/// fn add_pet(client: &rusqlite::Connection, name: &str, species: &str) -> Result<u64, rusqlite::Error> {
+///   query!(INSERT INTO pets (name, species) VALUES ($name, $species))
+/// }
+///
+/// #[inline_sql(backend = rusqlite)]
+/// fn get_pets_by_species(client: &rusqlite::Connection, species: &str) -> Result<Vec<Pet>, rusqlite::Error> {
+///   query!(SELECT * FROM pets WHERE species = $species ORDER BY name)
+/// }
+///
+/// #[inline_sql(backend = rusqlite)]
+/// fn get_pet_by_name(client: &rusqlite::Connection, name: &str) -> Result<Option<Pet>, rusqlite::Error> {
+///   query!(SELECT * FROM pets WHERE name = $name)
+/// }
+///
+/// #[inline_sql(backend = rusqlite)]
+/// fn get_species(client: &rusqlite::Connection, name: &str) -> Result<Option<String>, rusqlite::Error> {
+///   query!(SELECT species FROM pets WHERE name = $name)
+/// }
+///
+/// # fn main() -> Result<(), rusqlite::Error> {
+/// let client = rusqlite::Connection::open_in_memory()?;
+/// # client.execute_batch("CREATE TABLE pets (name TEXT PRIMARY KEY, species TEXT NOT NULL)")?;
+/// add_pet(&client, "Garfield", "cat")?;
+/// add_pet(&client, "Nermal", "cat")?;
+/// add_pet(&client, "Odie", "dog")?;
+///
+/// let cats = get_pets_by_species(&client, "cat")?;
+/// assert_eq!(cats.len(), 2);
+/// assert_eq!(cats[0].name, "Garfield");
+/// assert_eq!(cats[1].name, "Nermal");
+///
+/// let odie = get_pet_by_name(&client, "Odie")?.unwrap();
+/// assert_eq!(odie.species, "dog");
+/// assert!(get_pet_by_name(&client, "Jon")?.is_none());
+/// assert_eq!(get_species(&client, "Nermal")?.as_deref(), Some("cat"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Example: Use [`sqlx`].
+#[cfg_attr(feature = "sqlx", doc = "```")]
+#[cfg_attr(not(feature = "sqlx"), doc = "```ignore")]
+/// use futures::stream::{BoxStream, TryStreamExt};
+/// use inline_sql::inline_sql;
+/// use sqlx::sqlite::SqliteRow;
+///
+/// #[derive(sqlx::FromRow)]
+/// struct Pet {
+///   name: String,
+///   species: String,
+/// }
+///
+/// #[inline_sql(backend = sqlx)]
+/// async fn add_pet(client: &sqlx::SqlitePool, name: &str, species: &str) -> Result<u64, sqlx::Error> {
+///   query!(INSERT INTO pets (name, species) VALUES ($name, $species))
+/// }
+///
+/// #[inline_sql(backend = sqlx)]
+/// async fn get_pets_by_species(client: &sqlx::SqlitePool, species: &str) -> Result<Vec<Pet>, sqlx::Error> {
+///   query!(SELECT * FROM pets WHERE species = $species ORDER BY name)
+/// }
+///
+/// #[inline_sql(backend = sqlx)]
+/// async fn delete_pets_by_species(client: &mut sqlx::SqliteConnection, species: &str) -> Result<u64, sqlx::Error> {
+///   query!(DELETE FROM pets WHERE species = $species)
+/// }
+///
+/// #[inline_sql(backend = sqlx)]
+/// async fn get_pet_names(client: &sqlx::SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+///   query!(SELECT name FROM pets ORDER BY name)
+/// }
+///
+/// struct PetFilter {
+///   species: String,
+/// }
+///
+/// #[inline_sql(backend = sqlx)]
+/// async fn stream_pets<'a>(client: &'a sqlx::SqlitePool, filter: &PetFilter) -> Result<BoxStream<'a, Result<SqliteRow, sqlx::Error>>, sqlx::Error> {
+///   query!(SELECT * FROM pets WHERE species = $filter.species)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), sqlx::Error> {
+/// let client = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+/// # sqlx::query("CREATE TABLE pets (name TEXT PRIMARY KEY, species TEXT NOT NULL)").execute(&client).await?;
+/// add_pet(&client, "Garfield", "cat").await?;
+/// add_pet(&client, "Odie", "dog").await?;
+///
+/// let cats = get_pets_by_species(&client, "cat").await?;
+/// assert_eq!(cats.len(), 1);
+/// assert_eq!(cats[0].name, "Garfield");
+/// assert_eq!(get_pet_names(&client).await?, ["Garfield", "Odie"]);
+///
+/// let filter = PetFilter { species: "dog".into() };
+/// let dogs: Vec<_> = stream_pets(&client, &filter).await?.try_collect().await?;
+/// assert_eq!(dogs.len(), 1);
+///
+/// let mut transaction = client.begin().await?;
+/// assert_eq!(delete_pets_by_species(&mut transaction, "dog").await?, 1);
+/// transaction.commit().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`rusqlite`]: https://docs.rs/rusqlite
+/// [`rusqlite::Connection`]: https://docs.rs/rusqlite/latest/rusqlite/struct.Connection.html
+/// [`rusqlite::Connection::prepare_cached()`]: https://docs.rs/rusqlite/latest/rusqlite/struct.Connection.html#method.prepare_cached
+/// [`rusqlite::Error`]: https://docs.rs/rusqlite/latest/rusqlite/enum.Error.html
+/// [`rusqlite::Row`]: https://docs.rs/rusqlite/latest/rusqlite/struct.Row.html
+/// [`rusqlite::Transaction`]: https://docs.rs/rusqlite/latest/rusqlite/struct.Transaction.html
+/// [`sqlx`]: https://docs.rs/sqlx
+/// [`sqlx::Error`]: https://docs.rs/sqlx/latest/sqlx/enum.Error.html
+/// [`sqlx::Executor`]: https://docs.rs/sqlx/latest/sqlx/trait.Executor.html
+/// [`sqlx::FromRow`]: https://docs.rs/sqlx/latest/sqlx/trait.FromRow.html
+/// [`sqlx::PgConnection`]: https://docs.rs/sqlx/latest/sqlx/type.PgConnection.html
+/// [`sqlx::PgPool`]: https://docs.rs/sqlx/latest/sqlx/type.PgPool.html
+/// [`sqlx::SqlitePool`]: https://docs.rs/sqlx/latest/sqlx/type.SqlitePool.html
+/// [`sqlx::query::Query::bind()`]: https://docs.rs/sqlx/latest/sqlx/query/struct.Query.html#method.bind
 pub use inline_sql_macros::inline_sql;
 
+/// Derive [`FromRow`] and [`TryFrom`] for converting rows into a struct.
+///
+/// For structs with named fields, each field is decoded from the column with the same name.
+/// For tuple structs, each field is decoded from the column at the same position.
+///
+/// By default, the conversion is implemented for [`tokio_postgres::Row`] (which is also used by [`postgres`]).
+/// This requires the `tokio-postgres` or `postgres` feature.
+/// Use the `#[sql(backend = ...)]` attribute on the struct to select one or more backends, like `#[sql(backend = rusqlite)]`,
+/// which requires the `rusqlite` feature.
+/// For [`sqlx`], use [`sqlx::FromRow`] instead.
+///
+/// # Field attributes
+///
+/// * `#[sql(rename = "...")]`: Decode the field from a column with a different name.
+/// * `#[sql(default)]`: Use [`Default::default()`] if the column is missing from the row.
+/// * `#[sql(flatten)]`: Convert the whole row into the field, using the [`FromRow`] implementation of the field type.
+/// * `#[sql(prefix = "...")]`: Like `flatten`, but read the named columns of the field type with a prefix.
+///   Prefixes of nested flattened fields are combined.
+/// * `#[sql(skip)]`: Do not decode the field, but use [`Default::default()`].
+/// * `#[sql(try_from = Type)]`: Decode the column as `Type`, and convert it to the field type with [`TryFrom`].
+///
+/// # Example: Rename, convert and flatten fields.
+/// ```
+/// use inline_sql::{inline_sql, FromRow};
+///
+/// #[derive(FromRow)]
+/// struct Owner {
+///   #[sql(rename = "owner_name")]
+///   name: String,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Pet {
+///   name: String,
+///   #[sql(try_from = i32)]
+///   age: u8,
+///   #[sql(default)]
+///   species: Option<String>,
+///   #[sql(flatten)]
+///   owner: Owner,
+///   #[sql(skip)]
+///   visits: Vec<String>,
+/// }
+///
+/// #[inline_sql]
+/// async fn get_pets(client: &tokio_postgres::Client) -> Result<Vec<Pet>, tokio_postgres::Error> {
+///   query! {
+///     SELECT pets.name, pets.age, owners.name AS owner_name
+///     FROM pets JOIN owners ON owners.id = pets.owner_id
+///   }
+/// }
+/// ```
+///
+/// # Example: Flatten a join with column prefixes.
+/// A flattened field of type [`Option<T>`] is [`None`] if all columns of `T` are NULL, like the result of a `LEFT JOIN` without a match.
+/// ```
+/// use inline_sql::{inline_sql, FromRow};
+///
+/// #[derive(FromRow)]
+/// struct Pet {
+///   name: String,
+///   species: String,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct Owner {
+///   name: String,
+/// }
+///
+/// #[derive(FromRow)]
+/// struct PetWithOwner {
+///   #[sql(prefix = "pet_")]
+///   pet: Pet,
+///   #[sql(prefix = "owner_")]
+///   owner: Option<Owner>,
+/// }
+///
+/// #[inline_sql]
+/// async fn get_pets_with_owner(client: &tokio_postgres::Client) -> Result<Vec<PetWithOwner>, tokio_postgres::Error> {
+///   query! {
+///     SELECT pets.name AS pet_name, pets.species AS pet_species, owners.name AS owner_name
+///     FROM pets LEFT JOIN owners ON owners.id = pets.owner_id
+///   }
+/// }
+/// ```
+///
+/// [`sqlx`]: https://docs.rs/sqlx
+/// [`sqlx::FromRow`]: https://docs.rs/sqlx/latest/sqlx/trait.FromRow.html
+pub use inline_sql_macros::FromRow;
+
+/// Convert a row of a query result into a value.
+///
+/// This trait is implemented by [`#[derive(FromRow)]`][`macro@FromRow`], which also implements [`TryFrom`] for the row type.
+/// The [`#[inline_sql]`][`inline_sql`] macro uses the [`TryFrom`] implementation to convert rows.
+/// This trait takes the row by reference, so it can be used to convert a single row into multiple values.
+pub trait FromRow<Row>: Sized {
+	/// The error returned when the row can not be converted.
+	type Error;
+
+	/// Convert a row into a value.
+	fn from_row(row: &Row) -> Result<Self, Self::Error>;
+
+	/// Convert a row into a value, reading named columns with a prefix.
+	///
+	/// This is used for fields with the `#[sql(prefix = "...")]` attribute.
+	/// The default implementation ignores the prefix.
+	fn from_row_prefixed(row: &Row, prefix: &str) -> Result<Self, Self::Error> {
+		let _ = prefix;
+		Self::from_row(row)
+	}
+
+	/// Check if all columns of the value are NULL, reading named columns with a prefix.
+	///
+	/// This is used to convert rows into an [`Option<T>`], which is [`None`] if all columns are NULL.
+	/// The default implementation always returns `false`.
+	fn is_null(row: &Row, prefix: &str) -> Result<bool, Self::Error> {
+		let _ = (row, prefix);
+		Ok(false)
+	}
+}
+
+/// Convert a row into [`None`] if all columns of `T` are NULL, for example for the result of a `LEFT JOIN`.
+impl<Row, T: FromRow<Row>> FromRow<Row> for Option<T> {
+	type Error = T::Error;
+
+	fn from_row(row: &Row) -> Result<Self, Self::Error> {
+		Self::from_row_prefixed(row, "")
+	}
+
+	fn from_row_prefixed(row: &Row, prefix: &str) -> Result<Self, Self::Error> {
+		if T::is_null(row, prefix)? {
+			Ok(None)
+		} else {
+			T::from_row_prefixed(row, prefix).map(Some)
+		}
+	}
+
+	fn is_null(row: &Row, prefix: &str) -> Result<bool, Self::Error> {
+		T::is_null(row, prefix)
+	}
+}
+
+/// A boxed stream of converted rows.
+///
+/// Functions that return a `Result<RowStream<T, E>, E>` convert each row lazily while the stream is polled.
+/// The stream does not borrow the client, so it can only be used with backends that return an owned row stream, like [`tokio_postgres`].
+pub type RowStream<T, E> = futures::stream::BoxStream<'static, Result<T, E>>;
+
+/// Error for queries that must return exactly one row, but returned a different number of rows.
+///
+/// Functions that return a single row (`Result<T, E>`) require that `E` implements [`From<RowCountError>`].
+/// Otherwise, the compiler reports the missing implementation on the error type of the function:
+///
+/// ```compile_fail,E0277
+/// #[inline_sql::inline_sql]
+/// async fn count_pets(client: &tokio_postgres::Client) -> Result<i64, tokio_postgres::Error> {
+///     query!(SELECT count(*) FROM pets)
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RowCountError {
+	/// The query did not return any rows.
+	NoRows,
+
+	/// The query returned more than one row.
+	TooManyRows,
+}
+
+impl std::fmt::Display for RowCountError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::NoRows => write!(f, "query returned no rows, expected exactly one row"),
+			Self::TooManyRows => write!(f, "query returned more than one row, expected exactly one row"),
+		}
+	}
+}
+
+impl std::error::Error for RowCountError {}
+
+/// Error for queries that return tuples, but returned a different number of columns than the tuple has elements.
+///
+/// Functions that return tuples (like `Result<Vec<(A, B)>, E>`) or maps with scalar values (like `Result<HashMap<K, String>, E>`)
+/// require that `E` implements [`From<ColumnCountError>`].
+/// Otherwise, the compiler reports the missing implementation on the error type of the function:
+///
+/// ```compile_fail,E0277
+/// #[inline_sql::inline_sql]
+/// async fn get_pets(client: &tokio_postgres::Client) -> Result<Vec<(String, i32)>, tokio_postgres::Error> {
+///     query!(SELECT name, age FROM pets)
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ColumnCountError {
+	/// The number of elements in the tuple.
+	pub expected: usize,
+
+	/// The number of columns returned by the query.
+	pub actual: usize,
+}
+
+impl std::fmt::Display for ColumnCountError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "query returned {} columns, but the row type is a tuple with {} elements", self.actual, self.expected)
+	}
+}
+
+impl std::error::Error for ColumnCountError {}
+
+#[cfg(all(feature = "serde", any(feature = "tokio-postgres", feature = "postgres")))]
+pub mod serde;
+
 #[doc(hidden)]
 pub mod macro_export__ {
 	pub mod prelude {
 		pub use futures::StreamExt;
 	}
 
-	pub fn convert_row<F, T, E>(fun: F, row: tokio_postgres::Row) -> Result<T, E>
+	pub fn convert_row<F, R, T, E>(fun: F, row: R) -> Result<T, E>
 	where
-		F: Fn(tokio_postgres::Row) -> Result<T, E>
+		F: Fn(R) -> Result<T, E>
 	{
 		(fun)(row)
 	}
+
+	/// Add a row to the last group if the parent has the same key, or start a new group, for the `group_by` argument.
+	///
+	/// A missing child (for example from a `LEFT JOIN` without a match) still starts a new group, but is not added to it.
+	pub fn push_grouped<P, C, K, F>(output: &mut Vec<(P, Vec<C>)>, key: F, parent: P, child: Option<C>)
+	where
+		F: Fn(&P) -> K,
+		K: PartialEq,
+	{
+		if let Some((last, children)) = output.last_mut() {
+			if key(last) == key(&parent) {
+				children.extend(child);
+				return;
+			}
+		}
+		output.push((parent, child.into_iter().collect()));
+	}
+
+	/// Convert the groups into the element type of the returned `Vec`.
+	pub fn finish_grouped<P, C, T>(output: Vec<(P, Vec<C>)>) -> Vec<T>
+	where
+		T: From<(P, Vec<C>)>,
+	{
+		output.into_iter().map(T::from).collect()
+	}
+
+	/// Get the name of a column of a flattened field with a prefix.
+	pub fn prefixed<'a>(prefix: &'a str, name: &'a str) -> std::borrow::Cow<'a, str> {
+		if prefix.is_empty() {
+			std::borrow::Cow::Borrowed(name)
+		} else if name.is_empty() {
+			std::borrow::Cow::Borrowed(prefix)
+		} else {
+			std::borrow::Cow::Owned(format!("{prefix}{name}"))
+		}
+	}
+
+	#[cfg(feature = "tokio-postgres")]
+	pub use tokio_postgres as pg;
+
+	#[cfg(all(feature = "postgres", not(feature = "tokio-postgres")))]
+	pub use postgres as pg;
+
+	/// Decode a column as `T` and convert it to `U` with [`TryFrom`], for `#[sql(try_from = T)]` fields.
+	///
+	/// Conversion errors are reported as errors of [`FromSql`][`pg::types::FromSql`], so they end up in the error of the backend.
+	#[cfg(any(feature = "tokio-postgres", feature = "postgres"))]
+	pub struct TryFromSql<T, U> {
+		pub value: U,
+		_source: std::marker::PhantomData<fn() -> T>,
+	}
+
+	#[cfg(any(feature = "tokio-postgres", feature = "postgres"))]
+	impl<'a, T, U> pg::types::FromSql<'a> for TryFromSql<T, U>
+	where
+		T: pg::types::FromSql<'a>,
+		U: TryFrom<T>,
+		U::Error: std::error::Error + Send + Sync + 'static,
+	{
+		fn from_sql(ty: &pg::types::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+			let value = T::from_sql(ty, raw)?;
+			Self::convert(value)
+		}
+
+		fn from_sql_null(ty: &pg::types::Type) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+			let value = T::from_sql_null(ty)?;
+			Self::convert(value)
+		}
+
+		fn accepts(ty: &pg::types::Type) -> bool {
+			T::accepts(ty)
+		}
+	}
+
+	#[cfg(any(feature = "tokio-postgres", feature = "postgres"))]
+	impl<T, U> TryFromSql<T, U>
+	where
+		U: TryFrom<T>,
+		U::Error: std::error::Error + Send + Sync + 'static,
+	{
+		fn convert(value: T) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+			let value = U::try_from(value)?;
+			Ok(Self {
+				value,
+				_source: std::marker::PhantomData,
+			})
+		}
+	}
+
+	/// Deserialize a row with serde, for the `map_row = serde` argument.
+	#[cfg(all(feature = "serde", any(feature = "tokio-postgres", feature = "postgres")))]
+	pub fn serde_row<T, E>(row: pg::Row) -> Result<T, E>
+	where
+		T: ::serde::de::DeserializeOwned,
+		E: From<crate::serde::Error>,
+	{
+		crate::serde::from_row(&row).map_err(E::from)
+	}
+
+	/// Decode any column, only to check if it is NULL.
+	#[cfg(any(feature = "tokio-postgres", feature = "postgres"))]
+	pub struct IsNull(pub bool);
+
+	#[cfg(any(feature = "tokio-postgres", feature = "postgres"))]
+	impl<'a> pg::types::FromSql<'a> for IsNull {
+		fn from_sql(_ty: &pg::types::Type, _raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+			Ok(Self(false))
+		}
+
+		fn from_sql_null(_ty: &pg::types::Type) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+			Ok(Self(true))
+		}
+
+		fn accepts(_ty: &pg::types::Type) -> bool {
+			true
+		}
+	}
 }
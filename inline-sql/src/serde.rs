@@ -0,0 +1,801 @@
+//! Deserialize rows with [`serde`].
+//!
+//! This module is available with the `serde` feature.
+//! It is used by the `map_row = serde` argument of the [`#[inline_sql]`][`crate::inline_sql`] macro,
+//! but you can also use [`from_row()`] directly.
+//!
+//! # Example
+//! ```
+//! use inline_sql::inline_sql;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Owner {
+//!   name: String,
+//!   address: Address,
+//! }
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Address {
+//!   city: String,
+//!   zip_code: Option<String>,
+//! }
+//!
+//! #[derive(Debug)]
+//! enum Error {
+//!   Sql(tokio_postgres::Error),
+//!   Deserialize(inline_sql::serde::Error),
+//! }
+//!
+//! impl From<tokio_postgres::Error> for Error {
+//!   fn from(e: tokio_postgres::Error) -> Self {
+//!     Self::Sql(e)
+//!   }
+//! }
+//!
+//! impl From<inline_sql::serde::Error> for Error {
+//!   fn from(e: inline_sql::serde::Error) -> Self {
+//!     Self::Deserialize(e)
+//!   }
+//! }
+//!
+//! // The `address` column has the type `jsonb`.
+//! #[inline_sql(map_row = serde)]
+//! async fn get_owners(client: &tokio_postgres::Client) -> Result<Vec<Owner>, Error> {
+//!   query!(SELECT name, address FROM owners)
+//! }
+//! ```
+
+use ::serde::de::{self, Deserializer, IntoDeserializer, Visitor};
+
+use crate::macro_export__::pg;
+use pg::types::{FromSql, Type};
+
+/// Deserialize a row into any type that implements [`Deserialize`][::serde::Deserialize].
+///
+/// Structs and maps are deserialized by column name, tuples and sequences by column position.
+/// Other types are deserialized from the only column of the row.
+///
+/// Columns are decoded based on their SQL type:
+/// * `bool`, the integer types, `oid`, `real` and `double precision` are decoded as the matching Rust type.
+/// * `text`, `varchar`, `char(n)` and `name` are decoded as a string, and can also be deserialized as a unit enum variant.
+/// * `bytea` is decoded as bytes.
+/// * `json` and `jsonb` are decoded with [`serde_json`], so they can be deserialized into any type,
+///   like a nested struct, a [`serde_json::Value`] or a [`Json<T>`][`pg::types::Json`].
+/// * `uuid` is decoded as a hyphenated string, like `"67e55044-10b1-426f-9247-bb680e5fe0c8"`.
+/// * `date`, `time`, `timestamp` and `timestamptz` are decoded as ISO 8601 strings,
+///   like `"2024-03-10"`, `"12:30:00.5"`, `"2024-03-10T12:30:00"` and `"2024-03-10T12:30:00Z"`.
+///   Timestamps with a time zone are always in UTC.
+/// * `numeric` is decoded as a decimal string, like `"-12.50"`.
+/// * One-dimensional arrays of the types above are decoded as a sequence.
+/// * NULL is decoded as [`None`].
+///
+/// The string forms can be deserialized by the `serde` implementations of types like `uuid::Uuid`,
+/// `chrono::NaiveDate`, `chrono::DateTime<Utc>` and `rust_decimal::Decimal`.
+/// For other types, cast the column to one of the supported types in the query.
+pub fn from_row<'de, T: ::serde::Deserialize<'de>>(row: &'de pg::Row) -> Result<T, Error> {
+	T::deserialize(RowDeserializer::new(row))
+}
+
+/// A [`Deserializer`] for a row, as used by [`from_row()`].
+pub struct RowDeserializer<'de> {
+	/// The columns of the row.
+	columns: Vec<RawColumn<'de>>,
+}
+
+/// The name, SQL type and raw value of a column.
+struct RawColumn<'de> {
+	/// The name of the column.
+	name: &'de str,
+
+	/// The SQL type of the column.
+	sql_type: &'de Type,
+
+	/// The value in the binary format of PostgreSQL, or [`None`] for NULL.
+	raw: Option<&'de [u8]>,
+}
+
+impl<'de> RowDeserializer<'de> {
+	/// Create a deserializer for a row.
+	pub fn new(row: &'de pg::Row) -> Self {
+		let columns = row.columns().iter()
+			.enumerate()
+			.map(|(index, column)| RawColumn {
+				name: column.name(),
+				sql_type: column.type_(),
+				raw: row.try_get::<_, Option<RawValue>>(index)
+					.expect("every column type is accepted by `RawValue`")
+					.map(|value| value.0),
+			})
+			.collect();
+		Self { columns }
+	}
+
+	/// Get the only column of the row, for types that are not a struct, map or sequence.
+	fn single_column(&self) -> Result<ColumnDeserializer<'_, 'de>, Error> {
+		match self.columns.as_slice() {
+			[column] => Ok(ColumnDeserializer { column }),
+			columns => Err(Error::new(format!("query returned {} columns, but the row type is decoded from a single column", columns.len()))),
+		}
+	}
+}
+
+/// The raw value of a column of any SQL type.
+struct RawValue<'de>(&'de [u8]);
+
+impl<'de> FromSql<'de> for RawValue<'de> {
+	fn from_sql(_ty: &Type, raw: &'de [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+		Ok(Self(raw))
+	}
+
+	fn accepts(_ty: &Type) -> bool {
+		true
+	}
+}
+
+/// Error for rows that could not be deserialized.
+#[derive(Debug)]
+pub struct Error {
+	/// The name of the column that could not be deserialized.
+	column: Option<String>,
+
+	/// The error message.
+	message: String,
+
+	/// The error from decoding the column, if any.
+	source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl Error {
+	/// Create an error with a message.
+	fn new(message: impl std::fmt::Display) -> Self {
+		Self {
+			column: None,
+			message: message.to_string(),
+			source: None,
+		}
+	}
+
+	/// Create an error for a column that could not be decoded.
+	fn from_sql(error: Box<dyn std::error::Error + Send + Sync>) -> Self {
+		Self {
+			column: None,
+			message: error.to_string(),
+			source: Some(error),
+		}
+	}
+
+	/// Add the name of the column to the error, unless it already has one.
+	fn with_column(mut self, column: &str) -> Self {
+		if self.column.is_none() {
+			self.column = Some(column.to_owned());
+		}
+		self
+	}
+
+	/// The name of the column that could not be deserialized, if the error is about a single column.
+	pub fn column(&self) -> Option<&str> {
+		self.column.as_deref()
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match &self.column {
+			Some(column) => write!(f, "error deserializing column `{}`: {}", column, self.message),
+			None => write!(f, "error deserializing row: {}", self.message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.source.as_ref().map(|e| &**e as &(dyn std::error::Error + 'static))
+	}
+}
+
+impl de::Error for Error {
+	fn custom<T: std::fmt::Display>(message: T) -> Self {
+		Self::new(message)
+	}
+}
+
+/// Deserialize types that are not a struct, map or sequence from the only column of the row.
+macro_rules! forward_to_single_column {
+	($($method:ident $(($($arg:ident: $typ:ty),*))?,)*) => {
+		$(
+			fn $method<V: Visitor<'de>>(self, $($($arg: $typ,)*)? visitor: V) -> Result<V::Value, Self::Error> {
+				self.single_column()?.$method($($($arg,)*)? visitor)
+			}
+		)*
+	};
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'de> {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_map(visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_map(RowAccess { columns: &self.columns, index: 0 })
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_map(visitor)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_seq(RowAccess { columns: &self.columns, index: 0 })
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+		if self.columns.len() != len {
+			return Err(Error::new(crate::ColumnCountError { expected: len, actual: self.columns.len() }));
+		}
+		self.deserialize_seq(visitor)
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	// A row with a single column is NULL if that column is NULL.
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		if let [column] = self.columns.as_slice() {
+			if column.raw.is_none() {
+				return visitor.visit_none();
+			}
+		}
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_unit()
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_unit()
+	}
+
+	fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_unit()
+	}
+
+	forward_to_single_column! {
+		deserialize_bool,
+		deserialize_i8,
+		deserialize_i16,
+		deserialize_i32,
+		deserialize_i64,
+		deserialize_i128,
+		deserialize_u8,
+		deserialize_u16,
+		deserialize_u32,
+		deserialize_u64,
+		deserialize_u128,
+		deserialize_f32,
+		deserialize_f64,
+		deserialize_char,
+		deserialize_str,
+		deserialize_string,
+		deserialize_bytes,
+		deserialize_byte_buf,
+		deserialize_identifier,
+		deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+	}
+}
+
+/// Access the columns of a row as map entries by name, or as sequence elements by position.
+struct RowAccess<'a, 'de> {
+	/// The columns of the row.
+	columns: &'a [RawColumn<'de>],
+
+	/// The index of the next column.
+	index: usize,
+}
+
+impl<'a, 'de> RowAccess<'a, 'de> {
+	/// Deserialize the next column, and add the column name to errors.
+	fn next_column<T>(&mut self, deserialize: impl FnOnce(ColumnDeserializer<'a, 'de>) -> Result<T, Error>) -> Result<T, Error> {
+		let column = &self.columns[self.index];
+		self.index += 1;
+		deserialize(ColumnDeserializer { column }).map_err(|e| e.with_column(column.name))
+	}
+}
+
+impl<'de> de::MapAccess<'de> for RowAccess<'_, 'de> {
+	type Error = Error;
+
+	fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+		match self.columns.get(self.index) {
+			Some(column) => seed.deserialize(de::value::BorrowedStrDeserializer::new(column.name)).map(Some),
+			None => Ok(None),
+		}
+	}
+
+	fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+		self.next_column(|column| seed.deserialize(column))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.columns.len() - self.index)
+	}
+}
+
+impl<'de> de::SeqAccess<'de> for RowAccess<'_, 'de> {
+	type Error = Error;
+
+	fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+		if self.index < self.columns.len() {
+			self.next_column(|column| seed.deserialize(column)).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.columns.len() - self.index)
+	}
+}
+
+/// Deserialize a single column, based on its SQL type.
+struct ColumnDeserializer<'a, 'de> {
+	/// The column to deserialize.
+	column: &'a RawColumn<'de>,
+}
+
+impl<'de> ColumnDeserializer<'_, 'de> {
+	/// Decode the column as `T`.
+	fn get<T: FromSql<'de>>(&self) -> Result<T, Error> {
+		T::from_sql_nullable(self.column.sql_type, self.column.raw).map_err(Error::from_sql)
+	}
+
+	/// Check if the column is NULL.
+	fn is_null(&self) -> bool {
+		self.column.raw.is_none()
+	}
+
+	/// Get the SQL type of the column.
+	fn sql_type(&self) -> &'de Type {
+		self.column.sql_type
+	}
+
+	/// Decode an array column as a JSON array.
+	fn get_array<T>(&self) -> Result<serde_json::Value, Error>
+	where
+		T: FromSql<'de> + Into<serde_json::Value>,
+	{
+		Ok(self.get::<Vec<Option<T>>>()?.into())
+	}
+
+	/// Get the error for a column with an unsupported SQL type.
+	fn unsupported_type(&self) -> Error {
+		Error::new(format!("unsupported column type `{}`, cast the column to a supported type like `text`", self.sql_type()))
+	}
+}
+
+impl<'de> Deserializer<'de> for ColumnDeserializer<'_, 'de> {
+	type Error = Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		if self.is_null() {
+			return visitor.visit_none();
+		}
+		let array = match *self.sql_type() {
+			Type::BOOL => return visitor.visit_bool(self.get()?),
+			Type::CHAR => return visitor.visit_i8(self.get()?),
+			Type::INT2 => return visitor.visit_i16(self.get()?),
+			Type::INT4 => return visitor.visit_i32(self.get()?),
+			Type::INT8 => return visitor.visit_i64(self.get()?),
+			Type::OID => return visitor.visit_u32(self.get()?),
+			Type::FLOAT4 => return visitor.visit_f32(self.get()?),
+			Type::FLOAT8 => return visitor.visit_f64(self.get()?),
+			Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => return visitor.visit_borrowed_str(self.get()?),
+			Type::BYTEA => return visitor.visit_borrowed_bytes(self.get()?),
+			Type::JSON | Type::JSONB => self.get::<serde_json::Value>()?,
+			Type::UUID | Type::DATE | Type::TIME | Type::TIMESTAMP | Type::TIMESTAMPTZ | Type::NUMERIC => return visitor.visit_string(self.get::<TextForm>()?.0),
+			Type::BOOL_ARRAY => self.get_array::<bool>()?,
+			Type::CHAR_ARRAY => self.get_array::<i8>()?,
+			Type::INT2_ARRAY => self.get_array::<i16>()?,
+			Type::INT4_ARRAY => self.get_array::<i32>()?,
+			Type::INT8_ARRAY => self.get_array::<i64>()?,
+			Type::OID_ARRAY => self.get_array::<u32>()?,
+			Type::FLOAT4_ARRAY => self.get_array::<f32>()?,
+			Type::FLOAT8_ARRAY => self.get_array::<f64>()?,
+			Type::TEXT_ARRAY | Type::VARCHAR_ARRAY | Type::BPCHAR_ARRAY | Type::NAME_ARRAY => self.get_array::<String>()?,
+			Type::JSON_ARRAY | Type::JSONB_ARRAY => self.get_array::<serde_json::Value>()?,
+			Type::UUID_ARRAY | Type::DATE_ARRAY | Type::TIME_ARRAY | Type::TIMESTAMP_ARRAY | Type::TIMESTAMPTZ_ARRAY | Type::NUMERIC_ARRAY => self.get_array::<TextForm>()?,
+			_ => return Err(self.unsupported_type()),
+		};
+		array.deserialize_any(visitor).map_err(de::Error::custom)
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		if self.is_null() {
+			visitor.visit_none()
+		} else {
+			visitor.visit_some(self)
+		}
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+		match *self.sql_type() {
+			Type::JSON | Type::JSONB => {
+				self.get::<serde_json::Value>()?
+					.deserialize_enum(name, variants, visitor)
+					.map_err(de::Error::custom)
+			},
+			Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+				let variant: &'de str = self.get()?;
+				visitor.visit_enum(variant.into_deserializer())
+			},
+			_ => Err(self.unsupported_type()),
+		}
+	}
+
+	// Columns that are not used by the type are not decoded at all.
+	fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_unit()
+	}
+
+	::serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct seq tuple
+		tuple_struct map struct identifier
+	}
+}
+
+/// The text form of a column that has no matching type in [`serde`], like a UUID, a date or a decimal number.
+struct TextForm(String);
+
+impl<'de> FromSql<'de> for TextForm {
+	fn from_sql(ty: &Type, raw: &'de [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+		let text = match *ty {
+			Type::UUID => format_uuid(raw)?,
+			Type::DATE => format_date(read_i32(raw)?),
+			Type::TIME => format_time(read_i64(raw)?),
+			Type::TIMESTAMP => format_timestamp(read_i64(raw)?, ""),
+			Type::TIMESTAMPTZ => format_timestamp(read_i64(raw)?, "Z"),
+			Type::NUMERIC => format_numeric(raw)?,
+			_ => return Err(format!("can not decode `{ty}` as text").into()),
+		};
+		Ok(Self(text))
+	}
+
+	fn accepts(ty: &Type) -> bool {
+		matches!(*ty, Type::UUID | Type::DATE | Type::TIME | Type::TIMESTAMP | Type::TIMESTAMPTZ | Type::NUMERIC)
+	}
+}
+
+impl From<TextForm> for serde_json::Value {
+	fn from(value: TextForm) -> Self {
+		Self::String(value.0)
+	}
+}
+
+/// The number of microseconds in a day.
+const MICROSECONDS_PER_DAY: i64 = 24 * 60 * 60 * 1_000_000;
+
+/// The number of days from 1970-01-01 to 2000-01-01, the epoch of PostgreSQL.
+const POSTGRES_EPOCH_DAYS: i64 = 10_957;
+
+/// Read a big-endian `i32`.
+fn read_i32(raw: &[u8]) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+	Ok(i32::from_be_bytes(raw.try_into().map_err(|_| "invalid message length")?))
+}
+
+/// Read a big-endian `i64`.
+fn read_i64(raw: &[u8]) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+	Ok(i64::from_be_bytes(raw.try_into().map_err(|_| "invalid message length")?))
+}
+
+/// Format a UUID as hyphenated lowercase hexadecimal.
+fn format_uuid(raw: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+	if raw.len() != 16 {
+		return Err("invalid message length".into());
+	}
+	let mut text = String::with_capacity(36);
+	for (i, byte) in raw.iter().enumerate() {
+		if matches!(i, 4 | 6 | 8 | 10) {
+			text.push('-');
+		}
+		text.push_str(&format!("{byte:02x}"));
+	}
+	Ok(text)
+}
+
+/// Format a date, given as the number of days since 2000-01-01.
+fn format_date(days: i32) -> String {
+	match days {
+		i32::MAX => "infinity".into(),
+		i32::MIN => "-infinity".into(),
+		days => format_days(i64::from(days)),
+	}
+}
+
+/// Format a time of day, given as the number of microseconds since midnight.
+fn format_time(microseconds: i64) -> String {
+	let seconds = microseconds / 1_000_000;
+	let mut text = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+	push_fraction(&mut text, microseconds % 1_000_000);
+	text
+}
+
+/// Format a timestamp, given as the number of microseconds since 2000-01-01 00:00:00.
+fn format_timestamp(microseconds: i64, suffix: &str) -> String {
+	match microseconds {
+		i64::MAX => "infinity".into(),
+		i64::MIN => "-infinity".into(),
+		microseconds => {
+			let days = microseconds.div_euclid(MICROSECONDS_PER_DAY);
+			let time = microseconds.rem_euclid(MICROSECONDS_PER_DAY);
+			format!("{}T{}{}", format_days(days), format_time(time), suffix)
+		},
+	}
+}
+
+/// Format a date in the proleptic Gregorian calendar, given as the number of days since 2000-01-01.
+fn format_days(days: i64) -> String {
+	// Convert the days to a civil date, with years starting in March so the leap day is the last day of a year.
+	let days = days + POSTGRES_EPOCH_DAYS + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 { month + 3 } else { month - 9 };
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+	if (0..=9999).contains(&year) {
+		format!("{year:04}-{month:02}-{day:02}")
+	} else {
+		format!("{year:+05}-{month:02}-{day:02}")
+	}
+}
+
+/// Add the fractional seconds to a formatted time, without trailing zeros.
+fn push_fraction(text: &mut String, microseconds: i64) {
+	if microseconds != 0 {
+		let fraction = format!("{microseconds:06}");
+		text.push('.');
+		text.push_str(fraction.trim_end_matches('0'));
+	}
+}
+
+/// Format a numeric value from the binary format of PostgreSQL.
+///
+/// The value consists of base 10000 digits, the weight of the first digit, the sign and the number of decimal digits after the point.
+fn format_numeric(raw: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+	let read_u16 = |index: usize| -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+		let bytes = raw.get(2 * index..2 * index + 2).ok_or("invalid message length")?;
+		Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+	};
+	let digit_count = usize::from(read_u16(0)?);
+	let weight = i64::from(read_u16(1)? as i16);
+	let sign = read_u16(2)?;
+	let scale = usize::from(read_u16(3)?);
+	let digits = (0..digit_count)
+		.map(|i| read_u16(4 + i))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut text = match sign {
+		0x0000 => String::new(),
+		0x4000 => String::from("-"),
+		0xC000 => return Ok("NaN".into()),
+		0xD000 => return Ok("Infinity".into()),
+		0xF000 => return Ok("-Infinity".into()),
+		_ => return Err("invalid sign of numeric value".into()),
+	};
+
+	// The digit at `index` is multiplied by 10000 to the power of `weight - index`.
+	let digit = |index: i64| usize::try_from(index).ok().and_then(|index| digits.get(index)).copied().unwrap_or(0);
+	if weight < 0 {
+		text.push('0');
+	} else {
+		text.push_str(&digit(0).to_string());
+		for index in 1..=weight {
+			text.push_str(&format!("{:04}", digit(index)));
+		}
+	}
+	if scale > 0 {
+		let mut fraction = String::new();
+		let mut index = weight + 1;
+		while fraction.len() < scale {
+			fraction.push_str(&format!("{:04}", digit(index)));
+			index += 1;
+		}
+		text.push('.');
+		text.push_str(&fraction[..scale]);
+	}
+	Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pg::types::Json;
+
+	fn column<'a>(name: &'a str, sql_type: &'a Type, raw: Option<&'a [u8]>) -> RawColumn<'a> {
+		RawColumn { name, sql_type, raw }
+	}
+
+	fn deserialize<'de, T: ::serde::Deserialize<'de>>(columns: Vec<RawColumn<'de>>) -> Result<T, Error> {
+		T::deserialize(RowDeserializer { columns })
+	}
+
+	fn numeric(weight: i16, sign: u16, scale: u16, digits: &[u16]) -> Vec<u8> {
+		let mut raw = Vec::new();
+		raw.extend((digits.len() as u16).to_be_bytes());
+		raw.extend(weight.to_be_bytes());
+		raw.extend(sign.to_be_bytes());
+		raw.extend(scale.to_be_bytes());
+		for digit in digits {
+			raw.extend(digit.to_be_bytes());
+		}
+		raw
+	}
+
+	fn int4_array(values: &[Option<i32>]) -> Vec<u8> {
+		let mut raw = Vec::new();
+		raw.extend(1i32.to_be_bytes());
+		raw.extend(i32::from(values.contains(&None)).to_be_bytes());
+		raw.extend(Type::INT4.oid().to_be_bytes());
+		raw.extend((values.len() as i32).to_be_bytes());
+		raw.extend(1i32.to_be_bytes());
+		for value in values {
+			match value {
+				Some(value) => {
+					raw.extend(4i32.to_be_bytes());
+					raw.extend(value.to_be_bytes());
+				},
+				None => raw.extend((-1i32).to_be_bytes()),
+			}
+		}
+		raw
+	}
+
+	#[derive(Debug, PartialEq, ::serde::Deserialize)]
+	struct Address {
+		city: String,
+		zip_code: Option<String>,
+	}
+
+	#[derive(Debug, PartialEq, ::serde::Deserialize)]
+	#[serde(rename_all = "lowercase")]
+	enum Species {
+		Cat,
+		Dog,
+	}
+
+	#[derive(Debug, PartialEq, ::serde::Deserialize)]
+	struct Pet {
+		id: String,
+		name: String,
+		species: Species,
+		age: i32,
+		weight: f64,
+		vaccinated: bool,
+		born: String,
+		adopted_at: String,
+		price: String,
+		address: Address,
+		toys: Json<Vec<String>>,
+		scores: Vec<Option<i32>>,
+	}
+
+	#[test]
+	fn decode_by_sql_type() {
+		let uuid = [0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f, 0xe0, 0xc8];
+		let age = 3i32.to_be_bytes();
+		let weight = 4.5f64.to_be_bytes();
+		let born = 8835i32.to_be_bytes();
+		let adopted_at = (763_389_000_000_000i64 + 500_000).to_be_bytes();
+		let price = numeric(0, 0x0000, 2, &[12, 5000]);
+		let address = b"\x01{\"city\": \"Muncie\", \"zip_code\": null}";
+		let scores = int4_array(&[Some(7), None]);
+		let pet: Pet = deserialize(vec![
+			column("id", &Type::UUID, Some(&uuid)),
+			column("name", &Type::TEXT, Some(b"Garfield")),
+			column("species", &Type::VARCHAR, Some(b"cat")),
+			column("age", &Type::INT4, Some(&age)),
+			column("weight", &Type::FLOAT8, Some(&weight)),
+			column("vaccinated", &Type::BOOL, Some(&[1])),
+			column("born", &Type::DATE, Some(&born)),
+			column("adopted_at", &Type::TIMESTAMPTZ, Some(&adopted_at)),
+			column("price", &Type::NUMERIC, Some(&price)),
+			column("address", &Type::JSONB, Some(address)),
+			column("toys", &Type::JSON, Some(b"[\"lasagna\"]")),
+			column("scores", &Type::INT4_ARRAY, Some(&scores)),
+		]).unwrap();
+		assert_eq!(pet, Pet {
+			id: "67e55044-10b1-426f-9247-bb680e5fe0c8".into(),
+			name: "Garfield".into(),
+			species: Species::Cat,
+			age: 3,
+			weight: 4.5,
+			vaccinated: true,
+			born: "2024-03-10".into(),
+			adopted_at: "2024-03-10T12:30:00.5Z".into(),
+			price: "12.50".into(),
+			address: Address { city: "Muncie".into(), zip_code: None },
+			toys: Json(vec!["lasagna".into()]),
+			scores: vec![Some(7), None],
+		});
+		assert_ne!(Species::Dog, pet.species);
+	}
+
+	#[test]
+	fn decode_tuples_and_single_columns() {
+		let one = 1i64.to_be_bytes();
+		let (id, name): (i64, String) = deserialize(vec![column("id", &Type::INT8, Some(&one)), column("name", &Type::TEXT, Some(b"Odie"))]).unwrap();
+		assert_eq!((id, name.as_str()), (1, "Odie"));
+
+		let name: Option<String> = deserialize(vec![column("name", &Type::TEXT, None)]).unwrap();
+		assert_eq!(name, None);
+
+		let error = deserialize::<(i64, String, bool)>(vec![column("id", &Type::INT8, Some(&one))]).unwrap_err();
+		assert_eq!(error.to_string(), "error deserializing row: query returned 1 columns, but the row type is a tuple with 3 elements");
+
+		let error = deserialize::<i64>(vec![column("a", &Type::INT8, Some(&one)), column("b", &Type::INT8, Some(&one))]).unwrap_err();
+		assert_eq!(error.to_string(), "error deserializing row: query returned 2 columns, but the row type is decoded from a single column");
+	}
+
+	#[test]
+	fn missing_column() {
+		let error = deserialize::<Address>(vec![column("zip_code", &Type::TEXT, None)]).unwrap_err();
+		assert_eq!(error.to_string(), "error deserializing row: missing field `city`");
+		assert_eq!(error.column(), None);
+	}
+
+	#[test]
+	fn null_into_non_option() {
+		let error = deserialize::<Address>(vec![column("city", &Type::TEXT, None), column("zip_code", &Type::TEXT, None)]).unwrap_err();
+		assert_eq!(error.to_string(), "error deserializing column `city`: invalid type: Option value, expected a string");
+		assert_eq!(error.column(), Some("city"));
+	}
+
+	#[test]
+	fn unsupported_type() {
+		let error = deserialize::<Address>(vec![column("city", &Type::POINT, Some(&[0; 16]))]).unwrap_err();
+		assert_eq!(error.to_string(), "error deserializing column `city`: unsupported column type `point`, cast the column to a supported type like `text`");
+	}
+
+	#[test]
+	fn format_dates_and_times() {
+		assert_eq!(format_date(0), "2000-01-01");
+		assert_eq!(format_date(-10_957), "1970-01-01");
+		assert_eq!(format_date(8825), "2024-02-29");
+		assert_eq!(format_date(-730_485), "0000-01-01");
+		assert_eq!(format_date(-730_486), "-0001-12-31");
+		assert_eq!(format_date(i32::MAX), "infinity");
+		assert_eq!(format_date(i32::MIN), "-infinity");
+		assert_eq!(format_time(0), "00:00:00");
+		assert_eq!(format_time(45_296_000_123), "12:34:56.000123");
+		assert_eq!(format_timestamp(-1, ""), "1999-12-31T23:59:59.999999");
+		assert_eq!(format_timestamp(i64::MIN, "Z"), "-infinity");
+	}
+
+	#[test]
+	fn format_numerics() {
+		let format = |weight, sign, scale, digits: &[u16]| format_numeric(&numeric(weight, sign, scale, digits)).unwrap();
+		assert_eq!(format(0, 0x0000, 0, &[]), "0");
+		assert_eq!(format(0, 0x0000, 2, &[]), "0.00");
+		assert_eq!(format(1, 0x0000, 0, &[12, 3456]), "123456");
+		assert_eq!(format(2, 0x0000, 0, &[1]), "100000000");
+		assert_eq!(format(0, 0x4000, 3, &[12, 5000]), "-12.500");
+		assert_eq!(format(-1, 0x0000, 3, &[10]), "0.001");
+		assert_eq!(format(-2, 0x0000, 6, &[1234]), "0.000012");
+		assert_eq!(format(0, 0xC000, 0, &[]), "NaN");
+		assert_eq!(format(0, 0xF000, 0, &[]), "-Infinity");
+		assert!(format_numeric(&[0, 1]).is_err());
+	}
+}